@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::io;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Format a single event within a stack of `depth` spans, each with a handful of fields
+fn format_event_in_spans(depth: usize) {
+    let spans = (0..depth)
+        .map(|index| {
+            tracing::info_span!(
+                "a_span",
+                index,
+                user_id = "abc123",
+                http_request.request_method = "GET",
+                nested.field = true
+            )
+            .entered()
+        })
+        .collect::<Vec<_>>();
+
+    tracing::info!(foo = "bar", count = 42, "some stackdriver message");
+
+    drop(spans);
+}
+
+fn span_serialization(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("span_serialization");
+    let subscriber = Registry::default().with(tracing_stackdriver::layer().with_writer(io::sink));
+
+    tracing::subscriber::with_default(subscriber, || {
+        for depth in [1, 8, 32] {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(depth),
+                &depth,
+                |bencher, &depth| bencher.iter(|| format_event_in_spans(depth)),
+            );
+        }
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, span_serialization);
+criterion_main!(benches);
@@ -0,0 +1,232 @@
+use crate::{
+    internal_event::{internal_callsite, with_internal_event},
+    visitor::{log_metadata, Visitor},
+};
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing_core::{callsite::Callsite, span::Id, Event, Metadata};
+
+type Fields = serde_json::Map<String, serde_json::Value>;
+
+/// Field of repeat entries that carries the fields of the repeated Event as JSON
+const REPEATED_FIELDS: &str = "repeated_fields";
+
+/// Field of repeat entries that carries the target of the repeated Event
+const REPEATED_TARGET: &str = "repeated_target";
+
+internal_callsite!(
+    REPEAT_ENTRY_CALLSITE,
+    "repeat",
+    &[
+        "severity",
+        "source_location.file",
+        "source_location.line",
+        "repeat_count",
+        "first_seen",
+        "last_seen",
+        REPEATED_TARGET,
+        REPEATED_FIELDS,
+    ]
+);
+
+/// The most recent Event, and how often it has repeated since it was written
+struct Repeated {
+    metadata: &'static Metadata<'static>,
+    parent: Option<Id>,
+    target: String,
+    file: Option<String>,
+    line: Option<u32>,
+    fields: Fields,
+    repeat_count: u64,
+    first_seen: OffsetDateTime,
+    last_seen: OffsetDateTime,
+    last_seen_at: Instant,
+}
+
+/// The deduplication stage of a Layer, which collapses consecutive identical Events from the same
+/// callsite (with the same message, fields and span) into the first Event, followed by a single
+/// entry with a `repeat_count` once a different Event arrives, the timeout elapses, the span of the
+/// Event closes, or the Layer is dropped
+pub(crate) struct Deduplicator {
+    timeout: Duration,
+    last: Mutex<Option<Repeated>>,
+}
+
+impl Deduplicator {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Record an Event (within its resolved span, if any), returning whether it repeats the
+    /// previous Event (and should be skipped). Entries for the repeats of the previous Event are
+    /// handed to a callback once they end.
+    pub(crate) fn deduplicate(
+        &self,
+        event: &Event<'_>,
+        parent: Option<Id>,
+        callback: impl FnOnce(&Event<'_>),
+    ) -> bool {
+        let mut visitor = Visitor::default();
+        event.record(&mut visitor);
+
+        // Events bridged from the `log` crate share a callsite, and describe the target and
+        // location of the original record through `log.*` fields rather than their own metadata
+        let metadata = event.metadata();
+        let normalized_meta = log_metadata(event);
+        let normalized = normalized_meta.as_ref().unwrap_or(metadata);
+
+        if normalized_meta.is_some() {
+            visitor.strip_log_fields();
+        }
+
+        let now = Instant::now();
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(repeated) = last.as_mut().filter(|repeated| {
+            repeated.metadata.callsite() == metadata.callsite()
+                && repeated.parent == parent
+                && repeated.target == normalized.target()
+                && repeated.file.as_deref() == normalized.file()
+                && repeated.line == normalized.line()
+                && repeated.fields == visitor.values
+                && now.saturating_duration_since(repeated.last_seen_at) < self.timeout
+        }) {
+            repeated.repeat_count += 1;
+            repeated.last_seen = OffsetDateTime::now_utc();
+            repeated.last_seen_at = now;
+            return true;
+        }
+
+        let seen = OffsetDateTime::now_utc();
+        let previous = last.replace(Repeated {
+            metadata,
+            parent,
+            target: normalized.target().to_string(),
+            file: normalized.file().map(str::to_string),
+            line: normalized.line(),
+            fields: visitor.values,
+            repeat_count: 0,
+            first_seen: seen,
+            last_seen: seen,
+            last_seen_at: now,
+        });
+
+        // entries are written without holding the lock, in case writing them emits more Events
+        drop(last);
+
+        if let Some(previous) = previous {
+            let parent = previous.parent.clone();
+            previous.with_entry(parent.as_ref(), callback);
+        }
+
+        false
+    }
+
+    /// Hand the entry for the repeats of the previous Event to a callback if the timeout has elapsed
+    pub(crate) fn flush_expired(&self, callback: impl FnOnce(&Event<'_>)) {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if last
+            .as_ref()
+            .is_some_and(|repeated| repeated.last_seen_at.elapsed() >= self.timeout)
+        {
+            if let Some(repeated) = last.take() {
+                drop(last);
+                let parent = repeated.parent.clone();
+                repeated.with_entry(parent.as_ref(), callback);
+            }
+        }
+    }
+
+    /// Hand the entry for the repeats of the previous Event to a callback if it was recorded within
+    /// a span that is closing, while the span can still be resolved
+    pub(crate) fn flush_span(&self, id: &Id, callback: impl FnOnce(&Event<'_>)) {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if last
+            .as_ref()
+            .is_some_and(|repeated| repeated.parent.as_ref() == Some(id))
+        {
+            if let Some(repeated) = last.take() {
+                drop(last);
+                repeated.with_entry(Some(id), callback);
+            }
+        }
+    }
+
+    /// Hand the entry for the repeats of the previous Event to a callback regardless of the timeout,
+    /// as an explicit root (for when its span can no longer be resolved)
+    pub(crate) fn flush(&self, callback: impl FnOnce(&Event<'_>)) {
+        let repeated = self
+            .last
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+
+        if let Some(repeated) = repeated {
+            repeated.with_entry(None, callback);
+        }
+    }
+}
+
+impl Repeated {
+    /// Build the entry for the repeats of an Event (if it repeated at all) within a span, or as an
+    /// explicit root
+    fn with_entry(self, parent: Option<&Id>, callback: impl FnOnce(&Event<'_>)) {
+        if self.repeat_count == 0 {
+            return;
+        }
+
+        let fields = serde_json::Value::Object(self.fields).to_string();
+        let first_seen = self.first_seen.format(&Rfc3339).ok();
+        let last_seen = self.last_seen.format(&Rfc3339).ok();
+
+        with_internal_event(
+            &REPEAT_ENTRY_CALLSITE,
+            parent,
+            [
+                &self.metadata.level().as_str(),
+                &self.file.as_deref(),
+                &self.line,
+                &self.repeat_count,
+                &first_seen.as_deref(),
+                &last_seen.as_deref(),
+                &self.target.as_str(),
+                &fields.as_str(),
+            ],
+            callback,
+        );
+    }
+}
+
+/// Merge the fields of the repeated Event into the fields of a repeat entry, returning the target
+/// of the repeated Event
+pub(crate) fn merge_repeated_fields(
+    metadata: &Metadata<'_>,
+    fields: &mut Fields,
+) -> Option<String> {
+    if metadata.callsite() != REPEAT_ENTRY_CALLSITE.metadata().callsite() {
+        return None;
+    }
+
+    let target = match fields.remove(REPEATED_TARGET) {
+        Some(serde_json::Value::String(target)) => Some(target),
+        _ => None,
+    };
+
+    if let Some(serde_json::Value::String(repeated_fields)) = fields.remove(REPEATED_FIELDS) {
+        if let Ok(serde_json::Value::Object(repeated_fields)) =
+            serde_json::from_str(&repeated_fields)
+        {
+            fields.extend(repeated_fields);
+        }
+    }
+
+    target
+}
@@ -0,0 +1,206 @@
+//! Deserializable models of the JSON entries written by a Stackdriver
+//! [`Layer`](crate::Layer), for reading back its output in tests or log-processing tools.
+//!
+//! The models describe entries written in the (default) [`Format::Json`](crate::Format::Json)
+//! format with the (default) camelCase [`FieldNaming`](crate::FieldNaming). Keys within the
+//! `httpRequest` and `logging.googleapis.com/operation` fields follow the configured field naming,
+//! so entries written with another policy may not match them.
+//!
+//! ```rust
+//! use tracing_stackdriver::{entry::StackdriverEntry, LogSeverity};
+//!
+//! let line = r#"{
+//!   "time": "2024-01-01T00:00:00Z",
+//!   "severity": "INFO",
+//!   "target": "my_crate",
+//!   "message": "Signed in",
+//!   "userId": 42,
+//!   "logging.googleapis.com/labels": { "service": "checkout" }
+//! }"#;
+//!
+//! let entry: StackdriverEntry = serde_json::from_str(line).unwrap();
+//!
+//! assert_eq!(entry.severity, LogSeverity::Info);
+//! assert_eq!(entry.message.as_deref(), Some("Signed in"));
+//! assert_eq!(entry.labels["service"], "checkout");
+//! assert_eq!(entry.fields["userId"], 42);
+//! ```
+use crate::{LogSeverity, ServiceContext};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+
+type Fields = serde_json::Map<String, serde_json::Value>;
+
+/// A single entry, as the `jsonPayload` of a
+/// [LogEntry](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry)
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct StackdriverEntry {
+    /// Time at which the entry was written
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub time: Option<OffsetDateTime>,
+    /// Severity of the entry (`DEFAULT` for entries without a severity)
+    #[serde(default)]
+    pub severity: LogSeverity,
+    /// Target of the Event (or of the `log` record) that the entry describes
+    #[serde(default)]
+    pub target: String,
+    /// Message of the entry, if any
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Type of the entry, set to the `ReportedErrorEvent` type for entries picked up by Cloud
+    /// Error Reporting
+    #[serde(rename = "@type", default)]
+    pub entry_type: Option<String>,
+    /// Service that produced an error reported to Cloud Error Reporting
+    #[serde(rename = "serviceContext", default)]
+    pub service_context: Option<ServiceContext>,
+    /// Stack trace (or chain of error sources) of an error
+    #[serde(default)]
+    pub stack_trace: Option<String>,
+    /// Request that the entry describes
+    #[serde(rename = "httpRequest", default)]
+    pub http_request: Option<HttpRequest>,
+    /// Labels of the entry, from default labels and `labels.*` fields
+    #[serde(rename = "logging.googleapis.com/labels", default)]
+    pub labels: BTreeMap<String, String>,
+    /// Unique identifier of the entry, from an `insert_id` field
+    #[serde(rename = "logging.googleapis.com/insertId", default)]
+    pub insert_id: Option<String>,
+    /// Operation that the entry belongs to, from `operation.*` fields
+    #[serde(rename = "logging.googleapis.com/operation", default)]
+    pub operation: Option<Operation>,
+    /// Source location of the code that wrote the entry
+    #[serde(rename = "logging.googleapis.com/sourceLocation", default)]
+    pub source_location: Option<SourceLocation>,
+    /// Span that the entry was written in
+    #[serde(default)]
+    pub span: Option<Span>,
+    /// Every span in the current scope, from the root span to the current span
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    /// Cloud Trace fields of the entry
+    #[serde(flatten)]
+    pub trace: TraceFields,
+    /// Every other field of the entry, such as the fields of its Event and any static fields
+    #[serde(flatten)]
+    pub fields: Fields,
+}
+
+impl StackdriverEntry {
+    /// The span that the entry was written in, falling back to the current span for entries
+    /// without their own `span` field
+    pub fn parent_span(&self) -> Option<&Span> {
+        self.span.as_ref().or_else(|| self.spans.last())
+    }
+}
+
+/// Structured information about a request, as the `httpRequest` of a LogEntry.
+/// [See Google's HttpRequest docs here](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#HttpRequest).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpRequest {
+    /// HTTP Method of the request (e.g. GET, POST, etc)
+    #[serde(default)]
+    pub request_method: Option<String>,
+    /// URL of the request
+    #[serde(default)]
+    pub request_url: Option<String>,
+    /// Size of the HTTP request in bytes
+    #[serde(default)]
+    pub request_size: Option<u64>,
+    /// Size of the HTTP response in bytes
+    #[serde(default)]
+    pub response_size: Option<u64>,
+    /// HTTP status code of the response
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// User Agent string of the request
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// IP address of the client that issued the request
+    #[serde(default)]
+    pub remote_ip: Option<String>,
+    /// IP address of the server that the request was sent to
+    #[serde(default)]
+    pub server_ip: Option<String>,
+    /// Referer URL of the request
+    #[serde(default)]
+    pub referer: Option<String>,
+    /// Processing latency on the server, as a duration in seconds (e.g. `"0.1s"`)
+    #[serde(default)]
+    pub latency: Option<String>,
+    /// Whether or not a cache lookup was attempted
+    #[serde(default)]
+    pub cache_lookup: Option<bool>,
+    /// Whether or not an entity was served from cache (with or without validation)
+    #[serde(default)]
+    pub cache_hit: Option<bool>,
+    /// Whether or not the response was validated with the origin server before being served from cache
+    #[serde(default)]
+    pub cache_validated_with_origin_server: Option<bool>,
+    /// Number of HTTP response bytes inserted into cache
+    #[serde(default)]
+    pub cache_fill_bytes: Option<u64>,
+    /// Protocol used for the request (e.g. "HTTP/1.1", "HTTP/2", "websocket")
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// Every other `http_request.*` field of the entry
+    #[serde(flatten)]
+    pub fields: Fields,
+}
+
+/// Location in the source code that wrote an entry, as the `sourceLocation` of a LogEntry
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Source file name
+    pub file: String,
+    /// Line within the source file
+    #[serde(default)]
+    pub line: Option<String>,
+    /// Function or module that wrote the entry
+    #[serde(default)]
+    pub function: Option<String>,
+}
+
+/// Long-running operation that an entry belongs to, as the `operation` of a LogEntry
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Operation {
+    /// Identifier of the operation
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Arbitrary identifier of the operation's producer
+    #[serde(default)]
+    pub producer: Option<String>,
+    /// Whether or not this is the first entry of the operation
+    #[serde(default)]
+    pub first: Option<bool>,
+    /// Whether or not this is the last entry of the operation
+    #[serde(default)]
+    pub last: Option<bool>,
+}
+
+/// Span that an entry was written in, with its fields
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Span {
+    /// Name of the span
+    #[serde(alias = "spanName", default)]
+    pub name: Option<String>,
+    /// Fields recorded on the span
+    #[serde(flatten)]
+    pub fields: Fields,
+}
+
+/// Special fields correlating an entry with a trace in [Cloud Trace](https://cloud.google.com/trace)
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct TraceFields {
+    /// Resource name of the trace (e.g. `projects/my-project/traces/<trace ID>`)
+    #[serde(rename = "logging.googleapis.com/trace", default)]
+    pub trace: Option<String>,
+    /// Hex-encoded ID of the span within the trace
+    #[serde(rename = "logging.googleapis.com/spanId", default)]
+    pub span_id: Option<String>,
+    /// Whether or not the trace was sampled
+    #[serde(rename = "logging.googleapis.com/trace_sampled", default)]
+    pub trace_sampled: bool,
+}
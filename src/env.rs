@@ -0,0 +1,152 @@
+use crate::{
+    google::CloudTraceConfiguration,
+    layer::{layer, Layer},
+    naming::FieldNaming,
+};
+use std::{collections::BTreeMap, env::VarError, fs::OpenOptions, io, path::PathBuf, sync::Mutex};
+use tracing_core::Subscriber;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, registry::LookupSpan};
+
+/// Error returned when a [`Layer`] cannot be configured from environment variables
+#[derive(Debug, thiserror::Error)]
+pub enum EnvConfigError {
+    /// An environment variable was set to a value that isn't supported
+    #[error("{variable} must be {expected}, but was set to {value:?}")]
+    InvalidValue {
+        /// The name of the environment variable
+        variable: &'static str,
+        /// The value of the environment variable
+        value: String,
+        /// A description of the supported values
+        expected: &'static str,
+    },
+    /// The file configured through `STACKDRIVER_OUTPUT` couldn't be opened for writing
+    #[error("Unable to open STACKDRIVER_OUTPUT file {path:?}: {source}")]
+    Output {
+        /// The path of the file
+        path: PathBuf,
+        /// The error encountered while opening the file
+        source: io::Error,
+    },
+}
+
+impl<S> Layer<S, BoxMakeWriter>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    /// Create a Layer configured through environment variables, which are all optional:
+    ///
+    /// - `STACKDRIVER_SOURCE_LOCATION`: whether or not to include source locations (`true` or `false`)
+    /// - `STACKDRIVER_PROJECT_ID`: the Project ID for the Cloud Trace integration
+    /// - `STACKDRIVER_FIELD_CASE`: how to name field keys (`camel`, `snake` or `preserve`)
+    /// - `STACKDRIVER_OUTPUT`: where to write entries (`stdout`, `stderr`, or `file:<path>` to
+    ///   append to a file)
+    /// - `STACKDRIVER_LABELS`: labels for every entry, as comma-separated `key=value` pairs
+    ///
+    /// Unset or empty variables keep the default configuration, while invalid values are
+    /// reported as errors.
+    pub fn from_env() -> Result<Self, EnvConfigError> {
+        let writer = match env_var("STACKDRIVER_OUTPUT")?.as_deref() {
+            None | Some("stdout") => BoxMakeWriter::new(io::stdout),
+            Some("stderr") => BoxMakeWriter::new(io::stderr),
+            Some(output) => match output.strip_prefix("file:").filter(|path| !path.is_empty()) {
+                Some(path) => {
+                    let file = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .map_err(|source| EnvConfigError::Output {
+                            path: path.into(),
+                            source,
+                        })?;
+                    BoxMakeWriter::new(Mutex::new(file))
+                }
+                None => {
+                    return Err(invalid_value(
+                        "STACKDRIVER_OUTPUT",
+                        output,
+                        "one of `stdout`, `stderr` or `file:<path>`",
+                    ))
+                }
+            },
+        };
+
+        let mut layer = layer().with_writer(writer);
+
+        if let Some(source_location) = env_var("STACKDRIVER_SOURCE_LOCATION")? {
+            let include_source_location = match source_location.to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => {
+                    return Err(invalid_value(
+                        "STACKDRIVER_SOURCE_LOCATION",
+                        &source_location,
+                        "`true` or `false`",
+                    ))
+                }
+            };
+            layer = layer.with_source_location(include_source_location);
+        }
+
+        if let Some(project_id) = env_var("STACKDRIVER_PROJECT_ID")? {
+            layer = layer.with_cloud_trace(CloudTraceConfiguration { project_id });
+        }
+
+        if let Some(field_case) = env_var("STACKDRIVER_FIELD_CASE")? {
+            let field_naming = match field_case.to_lowercase().as_str() {
+                "camel" | "camelcase" => FieldNaming::CamelCase,
+                "snake" | "snake_case" => FieldNaming::SnakeCase,
+                "preserve" => FieldNaming::Preserve,
+                _ => {
+                    return Err(invalid_value(
+                        "STACKDRIVER_FIELD_CASE",
+                        &field_case,
+                        "one of `camel`, `snake` or `preserve`",
+                    ))
+                }
+            };
+            layer = layer.with_field_naming(field_naming);
+        }
+
+        if let Some(labels) = env_var("STACKDRIVER_LABELS")? {
+            let labels = labels
+                .split(',')
+                .map(|label| match label.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => {
+                        Ok((key.trim().to_string(), value.trim().to_string()))
+                    }
+                    _ => Err(invalid_value(
+                        "STACKDRIVER_LABELS",
+                        &labels,
+                        "a comma-separated list of `key=value` pairs",
+                    )),
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()?;
+            layer = layer.with_default_labels(labels);
+        }
+
+        Ok(layer)
+    }
+}
+
+/// Read an environment variable, treating empty values as unset
+fn env_var(variable: &'static str) -> Result<Option<String>, EnvConfigError> {
+    match std::env::var(variable) {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value.trim().to_string())),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(value)) => Err(invalid_value(
+            variable,
+            &value.to_string_lossy(),
+            "valid unicode",
+        )),
+    }
+}
+
+fn invalid_value(variable: &'static str, value: &str, expected: &'static str) -> EnvConfigError {
+    EnvConfigError::InvalidValue {
+        variable,
+        value: value.to_string(),
+        expected,
+    }
+}
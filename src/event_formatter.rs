@@ -1,22 +1,50 @@
+#[cfg(feature = "redaction")]
+use crate::redaction::Redaction;
 use crate::{
-    google::LogSeverity,
+    dedup,
+    google::{CloudTraceConfiguration, CloudTraceContext, LogSeverity, ServiceContext},
+    layer::HttpRequestInheritance,
+    naming::{FieldNames, FieldNaming},
+    operation::OperationState,
+    pretty::{self, Format},
     serializers::{SerializableContext, SerializableSpan, SourceLocation},
+    severity::SeverityMapper,
+    truncation::truncate_entry,
+    visitor::{log_metadata, SpanFields, Visitor},
     writer::WriteAdaptor,
 };
 #[cfg(feature = "opentelemetry")]
 use opentelemetry::trace::{SamplingDecision, TraceContextExt};
 use serde::ser::{SerializeMap, Serializer as _};
-use std::fmt;
+use std::{collections::BTreeMap, fmt, io};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
-use tracing_core::{field::Visit, Event, Subscriber};
+use tracing_core::{Event, Subscriber};
 use tracing_subscriber::{
     fmt::{
         format::{self, JsonFields},
         FmtContext, FormatEvent,
     },
-    registry::LookupSpan,
+    registry::{LookupSpan, SpanRef},
 };
 
+const REPORTED_ERROR_EVENT_TYPE: &str =
+    "type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent";
+
+/// Top-level keys written by the formatter itself (along with any `logging.googleapis.com/*`
+/// keys), which static fields never override
+const RESERVED_KEYS: [&str; 10] = [
+    "severity",
+    "time",
+    "target",
+    "message",
+    "httpRequest",
+    "span",
+    "spans",
+    "@type",
+    "serviceContext",
+    "stack_trace",
+];
+
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error(transparent)]
@@ -38,139 +66,162 @@ impl From<Error> for fmt::Error {
 /// Tracing Event formatter for Stackdriver layers
 pub struct EventFormatter {
     pub(crate) include_source_location: bool,
-    #[cfg(feature = "opentelemetry")]
-    pub(crate) cloud_trace_configuration: Option<crate::CloudTraceConfiguration>,
-}
-
-// Helper struct to capture event fields
-struct EventFieldVisitor<'a>(serde_json::Map<String, serde_json::Value>, &'a JsonFields);
-
-impl Visit for EventFieldVisitor<'_> {
-    fn record_f64(&mut self, field: &tracing_core::Field, value: f64) {
-        self.0.insert(
-            field.name().to_string(),
-            serde_json::Value::Number(serde_json::Number::from_f64(value).unwrap_or_else(|| {
-                // tracing::debug!(target: "tracing_stackdriver::event_formatter", "f64 is not finite, using 0.0 instead");
-                serde_json::Number::from(0)
-            })),
-        );
-    }
-
-    fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
-        self.0.insert(
-            field.name().to_string(),
-            serde_json::Value::Number(value.into()),
-        );
-    }
-
-    fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
-        self.0.insert(
-            field.name().to_string(),
-            serde_json::Value::Number(value.into()),
-        );
-    }
-
-    fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
-        self.0
-            .insert(field.name().to_string(), serde_json::Value::Bool(value));
-    }
-
-    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
-        self.0.insert(
-            field.name().to_string(),
-            serde_json::Value::String(value.to_string()),
-        );
-    }
-
-    fn record_error(
-        &mut self,
-        field: &tracing_core::Field,
-        value: &(dyn std::error::Error + 'static),
-    ) {
-        self.0.insert(
-            field.name().to_string(),
-            serde_json::Value::String(value.to_string()),
-        );
-    }
-
-    fn record_debug(&mut self, field: &tracing_core::Field, value: &dyn fmt::Debug) {
-        match field.name() {
-            // Skip fields that are actually log metadata that have already been handled
-            name if name.starts_with("log.") => (),
-            name if name.starts_with("event.") => (),
-            "message" => {
-                self.0.insert(
-                    "message".to_string(), // Use "message" as the key for the message field
-                    serde_json::Value::String(format!("{:?}", value)),
-                );
-            }
-            _ => {
-                self.0.insert(
-                    field.name().to_string(),
-                    serde_json::Value::String(format!("{:?}", value)),
-                );
-            }
-        }
-    }
+    pub(crate) max_entry_size: Option<usize>,
+    pub(crate) cloud_trace_configuration: Option<CloudTraceConfiguration>,
+    pub(crate) service_context: Option<ServiceContext>,
+    pub(crate) field_names: FieldNames,
+    pub(crate) span_field_names: FieldNames,
+    pub(crate) http_request_inheritance: HttpRequestInheritance,
+    pub(crate) severity_mapper: SeverityMapper,
+    #[cfg(feature = "redaction")]
+    pub(crate) redaction: Option<std::sync::Arc<Redaction>>,
+    pub(crate) default_labels: BTreeMap<String, String>,
+    pub(crate) static_fields: BTreeMap<String, serde_json::Value>,
+    pub(crate) format: Format,
 }
 
 impl EventFormatter {
     /// Internal event formatting for a given serializer
-    fn format_event<S>(
+    fn format_event<S, W>(
         &self,
-        context: &FmtContext<S, JsonFields>,
-        mut serializer: serde_json::Serializer<WriteAdaptor>,
+        span: Option<SpanRef<S>>,
+        leaf_span: Option<SpanRef<S>>,
+        mut serializer: serde_json::Serializer<W>,
         event: &Event,
     ) -> Result<(), Error>
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
+        W: io::Write,
     {
         let time = OffsetDateTime::now_utc().format(&Rfc3339)?;
-        let meta = event.metadata();
 
-        // attempt to resolve the explicit parent first. If that fails, fall back to the
-        // current span for backwards compatibility.
-        let span = event
-            .parent()
-            .and_then(|id| context.span(id))
-            .or_else(|| context.lookup_current());
+        // Events bridged from the `log` crate describe the target and location of the original
+        // record through `log.*` fields rather than their own metadata
+        let normalized_meta = log_metadata(event);
+        let meta = normalized_meta.as_ref().unwrap_or_else(|| event.metadata());
 
         // Extract event fields first
-        let mut visitor = EventFieldVisitor(serde_json::Map::new(), context.field_format());
+        let mut visitor = Visitor::default();
         event.record(&mut visitor);
+        let repeated_target = dedup::merge_repeated_fields(meta, &mut visitor.values);
+
+        if normalized_meta.is_some() {
+            visitor.strip_log_fields();
+        }
 
-        // Check if there's a custom severity in the fields, otherwise use the log level
-        let severity = visitor
-            .0
-            .remove("severity")
-            .map(LogSeverity::from)
-            .unwrap_or_else(|| LogSeverity::from(meta.level()));
+        // Check if there's a custom severity in the fields, otherwise use the log level (either of
+        // which can be re-mapped by the SeverityMapper)
+        let severity = visitor.values.remove("severity").map(LogSeverity::from);
+        let severity = self
+            .severity_mapper
+            .severity(meta, &visitor.values, severity);
+
+        // Redact sensitive fields before anything is serialized
+        #[cfg(feature = "redaction")]
+        if let Some(redaction) = self.redaction.as_ref() {
+            redaction.redact_fields(&mut visitor.values);
+            visitor.error_chain = visitor
+                .error_chain
+                .map(|error_chain| redaction.redact_str(&error_chain).into_owned());
+        }
 
         let mut map = serializer.serialize_map(None)?;
 
         map.serialize_entry("severity", &severity)?;
         map.serialize_entry("time", &time)?;
-        map.serialize_entry("target", meta.target())?;
+        map.serialize_entry(
+            "target",
+            repeated_target.as_deref().unwrap_or(meta.target()),
+        )?;
+
+        // Mark errors for pickup by Cloud Error Reporting, preferring explicit stack traces
+        // over the source chain of a recorded error
+        let stack_trace = visitor.values.remove("stack_trace");
+        let reported_error_context = self.service_context.as_ref().filter(|_| {
+            severity >= LogSeverity::Error
+                && (stack_trace.is_some() || visitor.error_chain.is_some())
+        });
+
+        if let Some(service_context) = reported_error_context {
+            map.serialize_entry("@type", REPORTED_ERROR_EVENT_TYPE)?;
+            map.serialize_entry("serviceContext", service_context)?;
+        }
+
+        if let Some(stack_trace) = stack_trace.as_ref() {
+            map.serialize_entry("stack_trace", stack_trace)?;
+        } else if let (Some(_), Some(error_chain)) =
+            (reported_error_context, visitor.error_chain.as_ref())
+        {
+            map.serialize_entry("stack_trace", error_chain)?;
+        }
 
-        // Process fields with special handling for http_request, labels, and insert_id
+        // Process fields with special handling for http_request, labels, operation, and insert_id
         let mut http_request = std::collections::BTreeMap::new();
-        let mut labels = std::collections::BTreeMap::new();
+        let mut labels = self
+            .default_labels
+            .iter()
+            .map(|(key, value)| (self.field_names.convert(key).into_owned(), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let mut operation = std::collections::BTreeMap::new();
+
+        // labels and operation fields recorded on spans apply to every event in their scope, with
+        // inner spans (and then the event itself) overriding the fields of outer spans (and any
+        // default labels)
+        if let Some(span_ref) = span.as_ref() {
+            for span in span_ref.scope().from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    for (key, value) in fields.0.iter() {
+                        if let Some(label_key) = key.strip_prefix("labels.") {
+                            labels.insert(
+                                self.field_names.convert(label_key).into_owned(),
+                                label_value(value.clone()),
+                            );
+                        } else if let Some(operation_key) = key.strip_prefix("operation.") {
+                            operation.insert(
+                                self.field_names.convert(operation_key).into_owned(),
+                                value.clone(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
 
-        for (key, value) in visitor.0 {
+        let mut source_location = std::collections::BTreeMap::new();
+
+        // static fields apply to every event, unless the event records a field of the same name or
+        // they would collide with a field written by the formatter itself
+        let static_fields = self
+            .static_fields
+            .iter()
+            .map(|(key, value)| (self.field_names.convert(key), value))
+            .filter(|(static_key, _)| {
+                !is_reserved_key(static_key)
+                    && !visitor
+                        .values
+                        .keys()
+                        .any(|key| *self.field_names.convert(key) == **static_key)
+            })
+            .collect::<Vec<_>>();
+
+        for (key, value) in visitor.values {
             let mut key_segments = key.splitn(2, '.');
 
             match (key_segments.next(), key_segments.next()) {
-                (Some("http_request"), Some(request_key)) => {
-                    use inflector::Inflector;
-                    http_request.insert(request_key.to_camel_case(), value);
+                (Some("http_request"), request_key) => {
+                    self.insert_http_request_field(&mut http_request, request_key, value);
                 }
                 (Some("labels"), Some(label_key)) => {
-                    use inflector::Inflector;
-                    let value = match value {
-                        serde_json::Value::String(value) => value,
-                        _ => value.to_string(),
-                    };
-                    labels.insert(label_key.to_camel_case(), value);
+                    labels.insert(
+                        self.field_names.convert(label_key).into_owned(),
+                        label_value(value),
+                    );
+                }
+                (Some("operation"), Some(operation_key)) => {
+                    operation.insert(self.field_names.convert(operation_key).into_owned(), value);
+                }
+                (Some("source_location"), Some(location_key)) => {
+                    source_location.insert(location_key.to_string(), value);
                 }
                 (Some("insert_id"), None) => {
                     let value = match value {
@@ -182,17 +233,52 @@ impl EventFormatter {
                 (Some("message"), None) => {
                     map.serialize_entry("message", &value)?;
                 }
-                (Some(key), None) => {
-                    use inflector::Inflector;
-                    map.serialize_entry(&key.to_camel_case(), &value)?;
-                }
                 _ => {
-                    use inflector::Inflector;
-                    map.serialize_entry(&key.to_camel_case(), &value)?;
+                    map.serialize_entry(&self.field_names.convert(&key), &value)?;
                 }
             }
         }
 
+        for (key, value) in static_fields {
+            map.serialize_entry(&key, value)?;
+        }
+
+        let inherit_http_request = match self.http_request_inheritance {
+            HttpRequestInheritance::Always => true,
+            HttpRequestInheritance::WithStatus => {
+                http_request.contains_key(self.field_names.convert("status").as_ref())
+            }
+            HttpRequestInheritance::Never => false,
+        };
+
+        // http_request fields recorded on spans describe every event in their scope, with inner
+        // spans (and then the event itself) overriding the fields of outer spans
+        if let (true, Some(span_ref)) = (inherit_http_request, span.as_ref()) {
+            let mut inherited = std::collections::BTreeMap::new();
+
+            for span in span_ref.scope().from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    for (key, value) in fields.0.iter() {
+                        let mut key_segments = key.splitn(2, '.');
+
+                        if let (Some("http_request"), request_key) =
+                            (key_segments.next(), key_segments.next())
+                        {
+                            self.insert_http_request_field(
+                                &mut inherited,
+                                request_key,
+                                value.clone(),
+                            );
+                        }
+                    }
+                }
+            }
+
+            for (request_key, value) in inherited {
+                http_request.entry(request_key).or_insert(value);
+            }
+        }
+
         if !http_request.is_empty() {
             map.serialize_entry("httpRequest", &http_request)?;
         }
@@ -201,122 +287,241 @@ impl EventFormatter {
             map.serialize_entry("logging.googleapis.com/labels", &labels)?;
         }
 
-        if self.include_source_location {
+        // the first entry within the nearest operation span is marked as the operation's first
+        let first_operation_entry = span.as_ref().and_then(|span_ref| {
+            span_ref.scope().find_map(|span| {
+                span.extensions()
+                    .get::<OperationState>()
+                    .map(OperationState::start)
+            })
+        });
+
+        if let Some(true) = first_operation_entry {
+            operation
+                .entry(self.field_names.convert("first").into_owned())
+                .or_insert(serde_json::Value::Bool(true));
+        }
+
+        if !operation.is_empty() {
+            map.serialize_entry("logging.googleapis.com/operation", &operation)?;
+        }
+
+        // explicit source_location fields take precedence over the event's own location
+        if let Some(serde_json::Value::String(file)) = source_location.get("file") {
+            let line = source_location.get("line").and_then(|line| match line {
+                serde_json::Value::String(line) => line.parse().ok(),
+                line => line.as_u64().and_then(|line| line.try_into().ok()),
+            });
+
+            map.serialize_entry(
+                "logging.googleapis.com/sourceLocation",
+                &SourceLocation {
+                    file,
+                    line,
+                    function: source_location
+                        .get("function")
+                        .and_then(serde_json::Value::as_str),
+                },
+            )?;
+        } else if self.include_source_location {
             if let Some(file) = meta.file() {
                 map.serialize_entry(
                     "logging.googleapis.com/sourceLocation",
                     &SourceLocation {
                         file,
                         line: meta.line(),
+                        function: None,
                     },
                 )?;
             }
         }
 
-        let spans_value = serde_json::to_value(SerializableContext::new(context))
-            .map_err(Error::Serialization)?;
-        let spans_array = spans_value.as_array();
-
         if let Some(span_ref) = span.as_ref() {
-            map.serialize_entry("span", &SerializableSpan::new(&span_ref))?;
-        } else if let Some(spans) = spans_array {
-            if let Some(last_span) = spans.last() {
-                map.serialize_entry("span", last_span)?;
-            }
+            map.serialize_entry(
+                "span",
+                &SerializableSpan::new(span_ref, &self.span_field_names),
+            )?;
         }
 
-        if spans_array.map_or(false, |arr| !arr.is_empty()) {
-            map.serialize_entry("spans", &spans_value)?;
+        if let Some(leaf_span) = leaf_span {
+            map.serialize_entry(
+                "spans",
+                &SerializableContext::new(&leaf_span, &self.span_field_names),
+            )?;
         }
 
-        #[cfg(feature = "opentelemetry")]
         if let (Some(span_ref), Some(config)) =
             (span.as_ref(), self.cloud_trace_configuration.as_ref())
         {
-            // Access OtelData to get the OpenTelemetry span information
-            // that was created by tracing_opentelemetry for this tracing span
-            if let Some(otel_data) = span_ref
-                .extensions()
-                .get::<tracing_opentelemetry::OtelData>()
+            #[cfg(feature = "opentelemetry")]
+            let trace_fields = TraceFields::from_opentelemetry(span_ref, config);
+            #[cfg(not(feature = "opentelemetry"))]
+            let trace_fields = None;
+
+            if let Some(trace_fields) =
+                trace_fields.or_else(|| TraceFields::from_trace_context(span_ref, config))
             {
-                let mut otel_trace_id: Option<String> = None;
-                let mut otel_span_id: Option<String> = None;
-                let mut otel_is_sampled: Option<bool> = None;
-
-                // Get trace ID and sampling from the parent context
-                // (trace IDs are propagated, not generated per span)
-                let parent_cx = &otel_data.parent_cx;
-                if parent_cx.has_active_span() {
-                    let parent_span = parent_cx.span();
-                    let parent_span_context = parent_span.span_context();
-                    otel_trace_id = Some(format!(
-                        "projects/{}/traces/{}",
-                        config.project_id,
-                        parent_span_context.trace_id()
-                    ));
-                    otel_is_sampled = Some(parent_span_context.is_sampled());
+                if let Some(trace_id) = trace_fields.trace_id {
+                    map.serialize_entry("logging.googleapis.com/trace", &trace_id)?;
                 }
-
-                if otel_trace_id.is_none() {
-                    if let Some(trace_id) = otel_data.builder.trace_id {
-                        otel_trace_id = Some(format!(
-                            "projects/{}/traces/{}",
-                            config.project_id,
-                            trace_id
-                        ));
-                    }
+                if let Some(span_id) = trace_fields.span_id {
+                    map.serialize_entry("logging.googleapis.com/spanId", &span_id)?;
                 }
-
-                if otel_is_sampled.is_none() {
-                    if let Some(sampling_result) = otel_data.builder.sampling_result.as_ref() {
-                        if matches!(sampling_result.decision, SamplingDecision::RecordAndSample) {
-                            otel_is_sampled = Some(true);
-                        }
-                    }
+                if let Some(true) = trace_fields.sampled {
+                    map.serialize_entry("logging.googleapis.com/trace_sampled", &true)?;
                 }
+            }
+        }
 
-                if otel_span_id.is_none() {
-                    if let Some(span_id) = otel_data.builder.span_id {
-                        otel_span_id = Some(span_id.to_string());
-                    }
-                }
+        map.end()?;
+        Ok(())
+    }
 
-                let current_cx = opentelemetry::Context::current();
-                if current_cx.has_active_span() {
-                    let current_span = current_cx.span();
-                    let current_span_context = current_span.span_context();
-                    if !current_span_context.is_remote() {
-                        if otel_trace_id.is_none() {
-                            otel_trace_id = Some(format!(
-                                "projects/{}/traces/{}",
-                                config.project_id,
-                                current_span_context.trace_id()
-                            ));
-                        }
-                        if otel_span_id.is_none() {
-                            otel_span_id = Some(current_span_context.span_id().to_string());
-                        }
-                    }
-                    if otel_is_sampled.is_none() && current_span_context.is_sampled() {
-                        otel_is_sampled = Some(true);
-                    }
-                }
+    /// Insert either a single `http_request.*` field, or every field of a whole `http_request`
+    /// recorded as a structured value or as its JSON representation
+    fn insert_http_request_field(
+        &self,
+        http_request: &mut std::collections::BTreeMap<String, serde_json::Value>,
+        request_key: Option<&str>,
+        value: serde_json::Value,
+    ) {
+        if let Some(request_key) = request_key {
+            http_request.insert(self.field_names.convert(request_key).into_owned(), value);
+            return;
+        }
 
-                // Write the Cloud Trace fields
-                if let Some(trace_id) = otel_trace_id {
-                    map.serialize_entry("logging.googleapis.com/trace", &trace_id)?;
+        let request = match value {
+            serde_json::Value::String(request) => serde_json::from_str(&request).ok(),
+            value => Some(value),
+        };
+
+        if let Some(serde_json::Value::Object(request)) = request {
+            for (request_key, value) in request {
+                http_request.insert(self.field_names.convert(&request_key).into_owned(), value);
+            }
+        }
+    }
+}
+
+/// Whether or not a top-level key is written by the formatter itself
+fn is_reserved_key(key: &str) -> bool {
+    RESERVED_KEYS.contains(&key) || key.starts_with("logging.googleapis.com/")
+}
+
+/// Labels are always strings, so other values are written in their JSON representation
+fn label_value(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value,
+        _ => value.to_string(),
+    }
+}
+
+/// Cloud Trace special fields for a LogEntry
+#[derive(Debug, Default)]
+struct TraceFields {
+    trace_id: Option<String>,
+    span_id: Option<String>,
+    sampled: Option<bool>,
+}
+
+impl TraceFields {
+    /// Resolve trace fields from the nearest span with a [`CloudTraceContext`] in its scope
+    fn from_trace_context<S>(span: &SpanRef<S>, config: &CloudTraceConfiguration) -> Option<Self>
+    where
+        S: for<'lookup> LookupSpan<'lookup>,
+    {
+        span.scope().find_map(|span| {
+            span.extensions()
+                .get::<CloudTraceContext>()
+                .map(|trace_context| Self {
+                    trace_id: Some(format!(
+                        "projects/{}/traces/{}",
+                        config.project_id, trace_context.trace_id
+                    )),
+                    span_id: trace_context.span_id.clone(),
+                    sampled: Some(trace_context.sampled),
+                })
+        })
+    }
+
+    /// Resolve trace fields from the OpenTelemetry data attached to a span by `tracing_opentelemetry`
+    #[cfg(feature = "opentelemetry")]
+    fn from_opentelemetry<S>(span: &SpanRef<S>, config: &CloudTraceConfiguration) -> Option<Self>
+    where
+        S: for<'lookup> LookupSpan<'lookup>,
+    {
+        // Access OtelData to get the OpenTelemetry span information
+        // that was created by tracing_opentelemetry for this tracing span
+        let extensions = span.extensions();
+        let otel_data = extensions.get::<tracing_opentelemetry::OtelData>()?;
+
+        let mut otel_trace_id: Option<String> = None;
+        let mut otel_span_id: Option<String> = None;
+        let mut otel_is_sampled: Option<bool> = None;
+
+        // Get trace ID and sampling from the parent context
+        // (trace IDs are propagated, not generated per span)
+        let parent_cx = &otel_data.parent_cx;
+        if parent_cx.has_active_span() {
+            let parent_span = parent_cx.span();
+            let parent_span_context = parent_span.span_context();
+            otel_trace_id = Some(format!(
+                "projects/{}/traces/{}",
+                config.project_id,
+                parent_span_context.trace_id()
+            ));
+            otel_is_sampled = Some(parent_span_context.is_sampled());
+        }
+
+        if otel_trace_id.is_none() {
+            if let Some(trace_id) = otel_data.builder.trace_id {
+                otel_trace_id = Some(format!(
+                    "projects/{}/traces/{}",
+                    config.project_id, trace_id
+                ));
+            }
+        }
+
+        if otel_is_sampled.is_none() {
+            if let Some(sampling_result) = otel_data.builder.sampling_result.as_ref() {
+                if matches!(sampling_result.decision, SamplingDecision::RecordAndSample) {
+                    otel_is_sampled = Some(true);
                 }
-                if let Some(span_id) = otel_span_id {
-                    map.serialize_entry("logging.googleapis.com/spanId", &span_id)?;
+            }
+        }
+
+        if otel_span_id.is_none() {
+            if let Some(span_id) = otel_data.builder.span_id {
+                otel_span_id = Some(span_id.to_string());
+            }
+        }
+
+        let current_cx = opentelemetry::Context::current();
+        if current_cx.has_active_span() {
+            let current_span = current_cx.span();
+            let current_span_context = current_span.span_context();
+            if !current_span_context.is_remote() {
+                if otel_trace_id.is_none() {
+                    otel_trace_id = Some(format!(
+                        "projects/{}/traces/{}",
+                        config.project_id,
+                        current_span_context.trace_id()
+                    ));
                 }
-                if let Some(true) = otel_is_sampled {
-                    map.serialize_entry("logging.googleapis.com/trace_sampled", &true)?;
+                if otel_span_id.is_none() {
+                    otel_span_id = Some(current_span_context.span_id().to_string());
                 }
             }
+            if otel_is_sampled.is_none() && current_span_context.is_sampled() {
+                otel_is_sampled = Some(true);
+            }
         }
 
-        map.end()?;
-        Ok(())
+        Some(Self {
+            trace_id: otel_trace_id,
+            span_id: otel_span_id,
+            sampled: otel_is_sampled,
+        })
     }
 }
 
@@ -333,8 +538,67 @@ where
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
     {
-        let serializer = serde_json::Serializer::new(WriteAdaptor::new(&mut writer));
-        self.format_event(context, serializer, event)?;
+        // attempt to resolve the explicit parent first. If that fails, fall back to the
+        // current span for backwards compatibility. Explicit roots (such as the Layer's own
+        // summaries) never inherit the current span.
+        let span = match event.is_root() {
+            true => None,
+            false => event
+                .parent()
+                .and_then(|id| context.span(id))
+                .or_else(|| context.lookup_current()),
+        };
+
+        // the list of spans describes the current scope, or the scope of an explicit parent
+        let leaf_span = match event.is_contextual() {
+            true => context.lookup_current(),
+            false => event.parent().and_then(|id| context.span(id)),
+        };
+
+        let ansi = writer.has_ansi_escapes();
+        self.write_entry(span, leaf_span, &mut writer, ansi, event)
+    }
+}
+
+impl EventFormatter {
+    /// Write the entry for an Event (within the scope of its span, if any) as a single line
+    pub(crate) fn write_entry<S>(
+        &self,
+        span: Option<SpanRef<S>>,
+        leaf_span: Option<SpanRef<S>>,
+        writer: &mut impl fmt::Write,
+        ansi: bool,
+        event: &Event,
+    ) -> fmt::Result
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        if let (Format::Json, None) = (self.format, self.max_entry_size) {
+            let serializer = serde_json::Serializer::new(WriteAdaptor::new(writer));
+            self.format_event(span, leaf_span, serializer, event)?;
+            return writeln!(writer);
+        }
+
+        let mut buffer = vec![];
+        self.format_event(
+            span,
+            leaf_span,
+            serde_json::Serializer::new(&mut buffer),
+            event,
+        )?;
+
+        if let Some(max_entry_size) = self.max_entry_size.filter(|size| buffer.len() > *size) {
+            buffer =
+                truncate_entry(&buffer, max_entry_size, &self.field_names).map_err(Error::from)?;
+        }
+
+        // pretty entries are rendered from the JSON entry, so they preview its exact contents
+        match self.format {
+            Format::Json => io::Write::write_all(&mut WriteAdaptor::new(writer), &buffer)
+                .map_err(Error::from)?,
+            Format::Pretty => pretty::write_pretty(writer, &buffer, ansi)?,
+        }
+
         writeln!(writer)
     }
 }
@@ -343,8 +607,19 @@ impl Default for EventFormatter {
     fn default() -> Self {
         Self {
             include_source_location: true,
-            #[cfg(feature = "opentelemetry")]
+            max_entry_size: None,
             cloud_trace_configuration: None,
+            service_context: None,
+            field_names: FieldNames::default(),
+            // span fields keep their recorded keys unless a naming policy is configured
+            span_field_names: FieldNames::new(FieldNaming::Preserve),
+            http_request_inheritance: HttpRequestInheritance::default(),
+            severity_mapper: SeverityMapper::default(),
+            #[cfg(feature = "redaction")]
+            redaction: None,
+            default_labels: BTreeMap::new(),
+            static_fields: BTreeMap::new(),
+            format: Format::default(),
         }
     }
 }
@@ -0,0 +1,428 @@
+use crate::google::MonitoredResource;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    fmt, io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex, OnceLock, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+const DEFAULT_ENDPOINT: &str = "https://logging.googleapis.com/v2/entries:write";
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_QUEUE_CAPACITY: usize = 16;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type TokenProvider = dyn Fn() -> Option<String> + Send + Sync;
+
+/// A MakeWriter that sends formatted events directly to the Cloud Logging
+/// [`entries:write`](https://cloud.google.com/logging/docs/reference/v2/rest/v2/entries/write) endpoint
+/// instead of relying on a logging agent to collect them from stdout.
+///
+/// Entries are buffered and sent in batches by a background thread, so that emitting events never
+/// blocks on the Logging API. Batches wait for the thread in a bounded queue, and batches that
+/// don't fit in a full queue are discarded. Any entries left in the buffer are sent when
+/// [`flush`](io::Write::flush) is called (which blocks until they have been sent) or when the last
+/// clone of the exporter is dropped (which blocks until every queued batch has been sent). Every
+/// request is bounded by a [timeout](CloudLoggingExporter::with_timeout), so neither can hang on
+/// an unresponsive endpoint.
+#[cfg_attr(docsrs, doc(cfg(feature = "exporter")))]
+#[derive(Clone)]
+pub struct CloudLoggingExporter {
+    inner: Arc<Inner>,
+}
+
+impl CloudLoggingExporter {
+    /// Create an exporter that writes to the `log_id` log of a `global` resource in the
+    /// provided project
+    pub fn new(project_id: impl Into<String>, log_id: impl AsRef<str>) -> Self {
+        let project_id = project_id.into();
+
+        Self {
+            inner: Arc::new(Inner::new(Config {
+                agent: agent(DEFAULT_TIMEOUT),
+                endpoint: DEFAULT_ENDPOINT.to_string(),
+                log_name: format!(
+                    "projects/{}/logs/{}",
+                    project_id,
+                    encode_log_id(log_id.as_ref())
+                ),
+                resource: MonitoredResource::global(project_id),
+                batch_size: DEFAULT_BATCH_SIZE,
+                queue_capacity: DEFAULT_QUEUE_CAPACITY,
+                timeout: DEFAULT_TIMEOUT,
+                token_provider: None,
+            })),
+        }
+    }
+
+    /// Create an exporter that writes to the `log_id` log of a monitored resource (e.g. one found by
+    /// a [`ResourceDetector`](crate::ResourceDetector)) in the resource's project
+    pub fn from_resource(resource: MonitoredResource, log_id: impl AsRef<str>) -> Self {
+        Self::new(
+            resource.project_id().unwrap_or_default().to_string(),
+            log_id,
+        )
+        .with_resource(resource)
+    }
+
+    /// Sets the URL that batches of entries are POSTed to (e.g. a local mock server)
+    pub fn with_endpoint(self, endpoint: impl Into<String>) -> Self {
+        self.map_config(|config| config.endpoint = endpoint.into())
+    }
+
+    /// Sets the monitored resource that all exported entries are attributed to
+    pub fn with_resource(self, resource: MonitoredResource) -> Self {
+        self.map_config(|config| config.resource = resource)
+    }
+
+    /// Sets the maximum number of entries buffered before a batch is sent
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        self.map_config(|config| config.batch_size = batch_size.max(1))
+    }
+
+    /// Sets the maximum number of batches waiting to be sent, beyond which new batches are discarded
+    pub fn with_queue_capacity(self, queue_capacity: usize) -> Self {
+        self.map_config(|config| config.queue_capacity = queue_capacity.max(1))
+    }
+
+    /// Sets the timeout of every request to the Logging API, including connecting to the endpoint
+    /// and reading its response
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.map_config(|config| {
+            config.agent = agent(timeout);
+            config.timeout = timeout;
+        })
+    }
+
+    /// Sets a provider for OAuth2 access tokens, sent as a `Bearer` token with every batch
+    pub fn with_token_provider<F>(self, token_provider: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.map_config(|config| config.token_provider = Some(Arc::new(token_provider)))
+    }
+
+    fn map_config(self, f: impl FnOnce(&mut Config)) -> Self {
+        let mut config = self.inner.config.clone();
+        f(&mut config);
+
+        Self {
+            inner: Arc::new(Inner::new(config)),
+        }
+    }
+
+    /// The number of entries discarded so far because the queue of batches was full
+    pub fn dropped_entries(&self) -> usize {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl io::Write for CloudLoggingExporter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let entries = buffer
+            .split(|byte| byte == &b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Map<String, Value>>(line).map(LogEntry::from))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let batch = {
+            let mut pending = self
+                .inner
+                .entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            pending.extend(entries);
+
+            if pending.len() >= self.inner.config.batch_size {
+                std::mem::take(&mut *pending)
+            } else {
+                vec![]
+            }
+        };
+
+        if !batch.is_empty() {
+            let length = batch.len();
+
+            if !self.inner.worker().try_send(Message::Batch(batch)) {
+                self.inner.dropped.fetch_add(length, Ordering::Relaxed);
+            }
+        }
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for CloudLoggingExporter {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl fmt::Debug for CloudLoggingExporter {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("CloudLoggingExporter")
+            .field("endpoint", &self.inner.config.endpoint)
+            .field("log_name", &self.inner.config.log_name)
+            .field("resource", &self.inner.config.resource)
+            .field("batch_size", &self.inner.config.batch_size)
+            .field("queue_capacity", &self.inner.config.queue_capacity)
+            .field("timeout", &self.inner.config.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared state between clones of an exporter
+struct Inner {
+    config: Config,
+    entries: Mutex<Vec<LogEntry>>,
+    dropped: AtomicUsize,
+    // started along with the first batch, so that configuring an exporter spawns no threads
+    worker: OnceLock<Worker>,
+}
+
+impl Inner {
+    fn new(config: Config) -> Self {
+        Self {
+            config,
+            entries: Mutex::default(),
+            dropped: AtomicUsize::new(0),
+            worker: OnceLock::new(),
+        }
+    }
+
+    fn worker(&self) -> &Worker {
+        self.worker
+            .get_or_init(|| Worker::spawn(self.config.clone()))
+    }
+
+    /// Send any buffered entries, waiting until every batch handed to the worker has been sent
+    fn flush(&self) -> io::Result<()> {
+        let batch =
+            std::mem::take(&mut *self.entries.lock().unwrap_or_else(PoisonError::into_inner));
+
+        if batch.is_empty() && self.worker.get().is_none() {
+            return Ok(());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.worker().send(Message::Flush(batch, sender));
+
+        receiver
+            .recv()
+            .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::BrokenPipe)))
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let batch =
+            std::mem::take(&mut *self.entries.lock().unwrap_or_else(PoisonError::into_inner));
+
+        if !batch.is_empty() {
+            self.worker().send(Message::Batch(batch));
+        }
+
+        if let Some(worker) = self.worker.take() {
+            worker.shutdown();
+        }
+    }
+}
+
+/// Work for the background thread of an exporter
+enum Message {
+    /// Send a batch of entries
+    Batch(Vec<LogEntry>),
+    /// Send a (possibly empty) batch of entries, and report whether every batch so far was sent
+    Flush(Vec<LogEntry>, mpsc::Sender<io::Result<()>>),
+}
+
+/// Background thread that sends batches of entries in the order they were handed over
+struct Worker {
+    sender: mpsc::SyncSender<Message>,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn(config: Config) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(config.queue_capacity);
+
+        let handle = thread::Builder::new()
+            .name("tracing-stackdriver-exporter".to_string())
+            .spawn(move || {
+                // errors of background batches are reported with the next flush, if any
+                let mut result = Ok(());
+
+                for message in receiver {
+                    match message {
+                        Message::Batch(batch) => {
+                            if let Err(error) = config.send(batch) {
+                                eprintln!(
+                                    "[tracing-stackdriver] Unable to export log entries: {error}"
+                                );
+                                result = Err(error);
+                            }
+                        }
+                        Message::Flush(batch, flushed) => {
+                            let sent = config.send(batch);
+                            let _ = flushed.send(std::mem::replace(&mut result, Ok(())).and(sent));
+                        }
+                    }
+                }
+            })
+            .expect("Unable to spawn tracing-stackdriver exporter thread");
+
+        Self { sender, handle }
+    }
+
+    /// Queue a message, waiting for room in the queue if it is full
+    fn send(&self, message: Message) {
+        // the worker only stops once every exporter has been dropped
+        let _ = self.sender.send(message);
+    }
+
+    /// Queue a message if there is room in the queue, returning whether or not it was queued
+    fn try_send(&self, message: Message) -> bool {
+        !matches!(
+            self.sender.try_send(message),
+            Err(mpsc::TrySendError::Full(_))
+        )
+    }
+
+    /// Wait for every batch handed to the worker to be sent
+    fn shutdown(self) {
+        drop(self.sender);
+
+        if self.handle.join().is_err() {
+            eprintln!("[tracing-stackdriver] Exporter thread panicked before shutting down");
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Config {
+    agent: ureq::Agent,
+    endpoint: String,
+    log_name: String,
+    resource: MonitoredResource,
+    batch_size: usize,
+    queue_capacity: usize,
+    timeout: Duration,
+    token_provider: Option<Arc<TokenProvider>>,
+}
+
+impl Config {
+    fn send(&self, entries: Vec<LogEntry>) -> io::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let body = serde_json::to_string(&WriteRequest {
+            log_name: &self.log_name,
+            resource: &self.resource,
+            entries: &entries,
+            partial_success: true,
+        })?;
+
+        let mut request = self
+            .agent
+            .post(&self.endpoint)
+            .set("Content-Type", "application/json");
+
+        if let Some(token) = self.token_provider.as_ref().and_then(|provider| provider()) {
+            request = request.set("Authorization", &format!("Bearer {token}"));
+        }
+
+        request
+            .send_string(&body)
+            .map_err(|error| io::Error::other(error.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Request body of the `entries:write` method
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteRequest<'a> {
+    log_name: &'a str,
+    resource: &'a MonitoredResource,
+    entries: &'a [LogEntry],
+    partial_success: bool,
+}
+
+/// LogEntry in the shape expected by the Logging API, where the special fields written by the
+/// EventFormatter become top-level LogEntry fields and everything else ends up in `jsonPayload`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insert_id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_request: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_location: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span_id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_sampled: Option<Value>,
+    json_payload: Map<String, Value>,
+}
+
+impl From<Map<String, Value>> for LogEntry {
+    fn from(mut fields: Map<String, Value>) -> Self {
+        Self {
+            severity: fields.remove("severity"),
+            timestamp: fields.remove("time"),
+            insert_id: fields.remove("logging.googleapis.com/insertId"),
+            labels: fields.remove("logging.googleapis.com/labels"),
+            http_request: fields.remove("httpRequest"),
+            source_location: fields.remove("logging.googleapis.com/sourceLocation"),
+            operation: fields.remove("logging.googleapis.com/operation"),
+            trace: fields.remove("logging.googleapis.com/trace"),
+            span_id: fields.remove("logging.googleapis.com/spanId"),
+            trace_sampled: fields.remove("logging.googleapis.com/trace_sampled"),
+            json_payload: fields,
+        }
+    }
+}
+
+/// Build an HTTP agent whose requests are bounded by a timeout
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(timeout).build()
+}
+
+/// URL-encode a log ID for use in a `logName`
+fn encode_log_id(log_id: &str) -> String {
+    log_id
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -1,14 +1,18 @@
-use serde::Serialize;
-use std::{convert::Infallible, fmt, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::Infallible, fmt, str::FromStr};
 use tracing_core::Level;
 
+/// The maximum size (in bytes) of a LogEntry accepted by Cloud Logging.
+/// [See Google's quota docs here](https://cloud.google.com/logging/quotas#log-limits).
+pub const MAX_ENTRY_SIZE: usize = 256 * 1024;
+
 /// The severity of the event described in a log entry, expressed as standard severity levels.
 /// [See Google's LogSeverity docs here](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#LogSeverity).
 #[cfg_attr(
     all(tracing_unstable, feature = "valuable"),
     derive(valuable::Valuable)
 )]
-#[derive(Debug, Default, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LogSeverity {
     /// Log entry has no assigned severity level
@@ -102,9 +106,12 @@ impl From<serde_json::Value> for LogSeverity {
 
 /// Typechecked HttpRequest structure for stucturally logging information about a request.
 /// [See Google's HttpRequest docs here](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#HttpRequest).
-#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
-#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
-#[derive(Default)]
+///
+/// Requests can be recorded on Events as a `http_request` field, either through their `Display`
+/// implementation (e.g. `http_request = %request`) or, with `valuable` support, as a `Valuable`.
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[cfg(feature = "http")]
+#[derive(Clone, Debug, Default)]
 pub struct HttpRequest {
     /// Valid HTTP Method for the request (e.g. GET, POST, etc)
     pub request_method: Option<http::Method>,
@@ -138,13 +145,171 @@ pub struct HttpRequest {
     pub protocol: Option<String>,
 }
 
-#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
-#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[cfg(feature = "http")]
 impl HttpRequest {
     /// Generate a new log-able HttpRequest structured log entry
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Adds the status and size of a response to the request
+    pub fn with_response<B>(self, response: &http::Response<B>) -> Self {
+        Self {
+            status: Some(response.status()),
+            response_size: content_length(response.headers()).or(self.response_size),
+            ..self
+        }
+    }
+
+    /// Sets the processing latency of the request
+    pub fn with_latency(self, latency: std::time::Duration) -> Self {
+        Self {
+            latency: Some(latency),
+            ..self
+        }
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[cfg(feature = "http")]
+impl<B> From<&http::Request<B>> for HttpRequest {
+    fn from(request: &http::Request<B>) -> Self {
+        let headers = request.headers();
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        // proxies (like Cloud Run's) report the original client as the first forwarded address
+        let remote_ip = header("x-forwarded-for")
+            .and_then(|addresses| addresses.split(',').next())
+            .and_then(|address| address.trim().parse().ok())
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<std::net::SocketAddr>()
+                    .map(|address| address.ip())
+            });
+
+        Self {
+            request_method: Some(request.method().clone()),
+            request_url: request_url(request),
+            request_size: content_length(headers),
+            user_agent: header("user-agent").map(str::to_string),
+            remote_ip,
+            referer: header("referer").and_then(|referer| referer.parse().ok()),
+            protocol: Some(protocol(request.version()).to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Requests and Responses are logged with the JSON representation of a LogEntry's HttpRequest
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[cfg(feature = "http")]
+impl fmt::Display for HttpRequest {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        formatter.write_str(&json)
+    }
+}
+
+#[cfg(feature = "http")]
+impl Serialize for HttpRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(request_method) = &self.request_method {
+            map.serialize_entry("requestMethod", request_method.as_str())?;
+        }
+        if let Some(request_url) = &self.request_url {
+            map.serialize_entry("requestUrl", request_url.as_str())?;
+        }
+        if let Some(request_size) = self.request_size {
+            map.serialize_entry("requestSize", &request_size)?;
+        }
+        if let Some(response_size) = self.response_size {
+            map.serialize_entry("responseSize", &response_size)?;
+        }
+        if let Some(status) = self.status {
+            map.serialize_entry("status", &status.as_u16())?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            map.serialize_entry("userAgent", user_agent)?;
+        }
+        if let Some(remote_ip) = self.remote_ip {
+            map.serialize_entry("remoteIp", &remote_ip)?;
+        }
+        if let Some(server_ip) = self.server_ip {
+            map.serialize_entry("serverIp", &server_ip)?;
+        }
+        if let Some(referer) = &self.referer {
+            map.serialize_entry("referer", referer.as_str())?;
+        }
+        if let Some(latency) = self.latency {
+            map.serialize_entry("latency", &format_duration(latency))?;
+        }
+        if let Some(cache_lookup) = self.cache_lookup {
+            map.serialize_entry("cacheLookup", &cache_lookup)?;
+        }
+        if let Some(cache_hit) = self.cache_hit {
+            map.serialize_entry("cacheHit", &cache_hit)?;
+        }
+        if let Some(cache_validated) = self.cache_validated_with_origin_server {
+            map.serialize_entry("cacheValidatedWithOriginServer", &cache_validated)?;
+        }
+        if let Some(cache_fill_bytes) = self.cache_fill_bytes {
+            map.serialize_entry("cacheFillBytes", &cache_fill_bytes)?;
+        }
+        if let Some(protocol) = &self.protocol {
+            map.serialize_entry("protocol", protocol)?;
+        }
+
+        map.end()
+    }
+}
+
+/// Server-side requests usually only include a path, so absolute URLs are reconstructed from
+/// the `Host` header where possible
+#[cfg(feature = "http")]
+fn request_url<B>(request: &http::Request<B>) -> Option<url::Url> {
+    let uri = request.uri();
+
+    if uri.scheme().is_some() {
+        return uri.to_string().parse().ok();
+    }
+
+    let headers = request.headers();
+    let host = headers.get(http::header::HOST)?.to_str().ok()?;
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|scheme| scheme.to_str().ok())
+        .unwrap_or("http");
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+
+    format!("{scheme}://{host}{path}").parse().ok()
+}
+
+#[cfg(feature = "http")]
+fn protocol(version: http::Version) -> &'static str {
+    match version {
+        http::Version::HTTP_09 => "HTTP/0.9",
+        http::Version::HTTP_10 => "HTTP/1.0",
+        http::Version::HTTP_2 => "HTTP/2",
+        http::Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/1.1",
+    }
+}
+
+#[cfg(feature = "http")]
+fn content_length(headers: &http::HeaderMap) -> Option<u32> {
+    headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse().ok())
 }
 
 #[cfg(all(tracing_unstable, feature = "valuable"))]
@@ -184,9 +349,7 @@ impl valuable::Valuable for HttpRequest {
         let remote_ip = self.remote_ip.map(|ip| ip.to_string());
         let server_ip = self.server_ip.map(|ip| ip.to_string());
         let referer = self.referer.as_ref().map(|url| url.to_string());
-        let latency = self
-            .latency
-            .map(|latency| format!("{}s", latency.as_secs_f32()));
+        let latency = self.latency.map(format_duration);
 
         let (fields, values): (Vec<_>, Vec<_>) = HTTP_REQUEST_FIELDS
             .iter()
@@ -234,8 +397,6 @@ impl valuable::Structable for HttpRequest {
 /// Configuration for projects looking to use the [Cloud Trace](https://cloud.google.com/trace) integration
 /// through [trace-specific fields](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#FIELDS.trace) in
 /// a LogEntry.
-#[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
-#[cfg(any(docsrs, feature = "opentelemetry"))]
 #[derive(Clone)]
 pub struct CloudTraceConfiguration {
     /// Google-provided [Project
@@ -243,3 +404,184 @@ pub struct CloudTraceConfiguration {
     /// prefixing and identifying collectecd traces.
     pub project_id: String,
 }
+
+/// Identifies the service that produced an error for
+/// [Cloud Error Reporting](https://cloud.google.com/error-reporting/docs/formatting-error-messages).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceContext {
+    /// Name of the service (e.g. a Cloud Run service or binary name)
+    pub service: String,
+    /// Version of the service (e.g. a release tag or git SHA)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+}
+
+impl ServiceContext {
+    /// Create a service context for the named service without a version
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            version: None,
+        }
+    }
+
+    /// Sets the version of the service
+    pub fn with_version(self, version: impl Into<String>) -> Self {
+        Self {
+            version: Some(version.into()),
+            ..self
+        }
+    }
+}
+
+/// Trace context propagated to a service through an
+/// [`X-Cloud-Trace-Context`](https://cloud.google.com/trace/docs/trace-context#legacy-http-header) or W3C
+/// [`traceparent`](https://www.w3.org/TR/trace-context/#traceparent-header) header.
+///
+/// Spans with a `trace_context` field containing either header value are correlated with the trace
+/// through the special Cloud Trace LogEntry fields, without requiring OpenTelemetry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloudTraceContext {
+    /// 32-character hex-encoded trace ID
+    pub trace_id: String,
+    /// 16-character hex-encoded span ID, if the header included one
+    pub span_id: Option<String>,
+    /// Whether or not the trace was sampled
+    pub sampled: bool,
+}
+
+impl CloudTraceContext {
+    /// Parse an `X-Cloud-Trace-Context` header value (`TRACE_ID/SPAN_ID;o=OPTIONS`), where the
+    /// span ID and options are optional and the span ID is a decimal number.
+    pub fn from_cloud_trace_header(header: &str) -> Result<Self, ParseTraceContextError> {
+        let error = || ParseTraceContextError(header.to_string());
+        let (ids, options) = header.trim().split_once(';').unwrap_or((header.trim(), ""));
+        let (trace_id, span_id) = ids.split_once('/').unwrap_or((ids, ""));
+
+        if !is_hex(trace_id, 32) {
+            return Err(error());
+        }
+
+        let span_id = match span_id {
+            "" => None,
+            span_id => match span_id.parse::<u64>().map_err(|_| error())? {
+                0 => None,
+                span_id => Some(format!("{span_id:016x}")),
+            },
+        };
+
+        Ok(Self {
+            trace_id: trace_id.to_lowercase(),
+            span_id,
+            sampled: options.trim() == "o=1",
+        })
+    }
+
+    /// Parse a W3C `traceparent` header value (`VERSION-TRACE_ID-SPAN_ID-FLAGS`)
+    pub fn from_traceparent(header: &str) -> Result<Self, ParseTraceContextError> {
+        let error = || ParseTraceContextError(header.to_string());
+        let mut parts = header.trim().split('-');
+
+        let (Some(version), Some(trace_id), Some(span_id), Some(flags)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(error());
+        };
+
+        let flags = u8::from_str_radix(flags, 16).map_err(|_| error())?;
+
+        if !is_hex(version, 2)
+            || version.eq_ignore_ascii_case("ff")
+            || (version == "00" && parts.next().is_some())
+            || !is_hex(trace_id, 32)
+            || !is_hex(span_id, 16)
+            || trace_id.bytes().all(|byte| byte == b'0')
+            || span_id.bytes().all(|byte| byte == b'0')
+        {
+            return Err(error());
+        }
+
+        Ok(Self {
+            trace_id: trace_id.to_lowercase(),
+            span_id: Some(span_id.to_lowercase()),
+            sampled: flags & 1 == 1,
+        })
+    }
+}
+
+/// Parses either header format, preferring `traceparent` when the value is dash-delimited
+impl FromStr for CloudTraceContext {
+    type Err = ParseTraceContextError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        if string.contains('-') {
+            Self::from_traceparent(string)
+        } else {
+            Self::from_cloud_trace_header(string)
+        }
+    }
+}
+
+/// Formats the context as an `X-Cloud-Trace-Context` header value
+impl fmt::Display for CloudTraceContext {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.trace_id)?;
+
+        if let Some(span_id) = self
+            .span_id
+            .as_ref()
+            .and_then(|span_id| u64::from_str_radix(span_id, 16).ok())
+        {
+            write!(formatter, "/{span_id}")?;
+        }
+
+        write!(formatter, ";o={}", u8::from(self.sampled))
+    }
+}
+
+/// Error returned for header values that aren't a valid trace context
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid trace context header: {0:?}")]
+pub struct ParseTraceContextError(String);
+
+fn is_hex(string: &str, length: usize) -> bool {
+    string.len() == length && string.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Format a Duration in the `"1.234s"` format used by the Logging API (e.g. for `latency`)
+pub(crate) fn format_duration(duration: std::time::Duration) -> String {
+    let nanos = format!("{:09}", duration.subsec_nanos());
+    let nanos = nanos.trim_end_matches('0');
+
+    if nanos.is_empty() {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}.{nanos}s", duration.as_secs())
+    }
+}
+
+/// A [monitored resource](https://cloud.google.com/logging/docs/reference/v2/rest/v2/MonitoredResource)
+/// identifying the source of a LogEntry (e.g. a Cloud Run revision or a GCE instance).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MonitoredResource {
+    /// The monitored resource type (e.g. `global`, `cloud_run_revision`, `k8s_container`)
+    #[serde(rename = "type")]
+    pub r#type: String,
+    /// Labels that identify the specific resource, as required by the resource type
+    pub labels: BTreeMap<String, String>,
+}
+
+impl MonitoredResource {
+    /// Create a `global` monitored resource for the provided Project ID
+    pub fn global(project_id: impl Into<String>) -> Self {
+        Self {
+            r#type: "global".to_string(),
+            labels: BTreeMap::from([("project_id".to_string(), project_id.into())]),
+        }
+    }
+
+    /// The Project ID label of this resource, if there is one
+    pub fn project_id(&self) -> Option<&str> {
+        self.labels.get("project_id").map(String::as_str)
+    }
+}
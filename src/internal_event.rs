@@ -0,0 +1,51 @@
+use tracing_core::{callsite::DefaultCallsite, field::Value, span::Id, Callsite, Event};
+
+/// Declare the callsite of entries written by the Layer itself (rather than by instrumented code),
+/// which have no source location of their own
+macro_rules! internal_callsite {
+    ($callsite:ident, $name:literal, $fields:expr) => {
+        static $callsite: tracing_core::callsite::DefaultCallsite = {
+            static METADATA: tracing_core::Metadata<'static> = tracing_core::Metadata::new(
+                $name,
+                "tracing_stackdriver",
+                tracing_core::Level::INFO,
+                None,
+                None,
+                None,
+                tracing_core::field::FieldSet::new(
+                    $fields,
+                    tracing_core::identify_callsite!(&$callsite),
+                ),
+                tracing_core::Kind::EVENT,
+            );
+
+            tracing_core::callsite::DefaultCallsite::new(&METADATA)
+        };
+    };
+}
+
+pub(crate) use internal_callsite;
+
+/// Build an Event (within a span, if provided) from values for each of an internal callsite's
+/// fields (in the order they were declared), and hand it to a callback
+pub(crate) fn with_internal_event<const N: usize>(
+    callsite: &'static DefaultCallsite,
+    parent: Option<&Id>,
+    values: [&dyn Value; N],
+    callback: impl FnOnce(&Event<'_>),
+) {
+    let metadata = callsite.metadata();
+    let mut fields = metadata.fields().iter();
+    let fields: [_; N] = std::array::from_fn(|_| {
+        fields
+            .next()
+            .expect("Internal events should provide a value for every field")
+    });
+    let values: [_; N] = std::array::from_fn(|index| (&fields[index], Some(values[index])));
+
+    callback(&Event::new_child_of(
+        parent.cloned(),
+        metadata,
+        &metadata.fields().value_set(&values),
+    ));
+}
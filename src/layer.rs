@@ -1,5 +1,12 @@
-use crate::event_formatter::EventFormatter;
-use std::{fmt, io, ops::Deref};
+use crate::{
+    dedup::Deduplicator,
+    event_formatter::EventFormatter,
+    operation::{self, OperationState},
+    sampling::{self, Sampler},
+    span_events::{self, SpanTimings},
+    visitor::{SpanFields, Visitor},
+};
+use std::{fmt, io, ops::Deref, time::Duration};
 use tracing_core::{Event, Subscriber};
 use tracing_subscriber::{
     fmt::{format::JsonFields, MakeWriter},
@@ -29,19 +36,64 @@ pub fn layer<S>() -> Layer<S>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
 {
-    Layer(
-        tracing_subscriber::fmt::layer()
-            .json()
-            .event_format(EventFormatter::default()),
-    )
+    Layer {
+        inner: Some(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .event_format(EventFormatter::default()),
+        ),
+        ansi: false,
+        severity_mapper: crate::SeverityMapper::default(),
+        span_close_entries: false,
+        #[cfg(feature = "redaction")]
+        redaction: None,
+        sampler: None,
+        deduplicator: None,
+    }
+}
+
+/// Install a Stackdriver Layer with the default configuration as the global default subscriber,
+/// along with a bridge that forwards records from the `log` crate as Events
+///
+/// # Panics
+/// Panics if a global default subscriber or `log` logger has already been set (see
+/// [`try_init`] for a fallible alternative)
+#[cfg(feature = "log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log")))]
+pub fn init() {
+    try_init().expect("Unable to install global subscriber")
+}
+
+/// Install a Stackdriver Layer with the default configuration as the global default subscriber,
+/// along with a bridge that forwards records from the `log` crate as Events, failing if either
+/// has already been set
+#[cfg(feature = "log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log")))]
+pub fn try_init() -> Result<(), tracing_subscriber::util::TryInitError> {
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+    // the registry installs a LogTracer as well, since the log feature enables tracing-subscriber's
+    // tracing-log feature
+    tracing_subscriber::registry().with(layer()).try_init()
 }
 
 /// A tracing-compatible Layer implementation for Stackdriver
-pub struct Layer<S, W = fn() -> io::Stdout>(
-    tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>,
-)
+pub struct Layer<S, W = fn() -> io::Stdout>
 where
-    S: Subscriber + for<'span> LookupSpan<'span>;
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    // only taken when the Layer is dropped, to write any entries that are still pending
+    inner: Option<tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>>,
+    ansi: bool,
+    // also resolves the severity of Events for sampling, ahead of the event formatter
+    severity_mapper: crate::SeverityMapper,
+    span_close_entries: bool,
+    #[cfg(feature = "redaction")]
+    redaction: Option<std::sync::Arc<crate::Redaction>>,
+    sampler: Option<Sampler>,
+    deduplicator: Option<Deduplicator>,
+}
 
 impl<S, W> Layer<S, W>
 where
@@ -49,30 +101,247 @@ where
     W: for<'writer> MakeWriter<'writer> + 'static,
 {
     /// Sets the MakeWriter that the Layer being built will use to write events.
-    pub fn with_writer<M>(self, make_writer: M) -> Layer<S, M>
+    pub fn with_writer<M>(mut self, make_writer: M) -> Layer<S, M>
     where
         M: for<'writer> MakeWriter<'writer> + 'static,
     {
-        Layer(self.0.with_writer(make_writer))
+        Layer {
+            inner: self
+                .inner
+                .take()
+                .map(|inner| inner.with_writer(make_writer)),
+            ansi: self.ansi,
+            severity_mapper: std::mem::take(&mut self.severity_mapper),
+            span_close_entries: self.span_close_entries,
+            #[cfg(feature = "redaction")]
+            redaction: self.redaction.take(),
+            sampler: self.sampler.take(),
+            deduplicator: self.deduplicator.take(),
+        }
+    }
+
+    /// Configures whether or not an entry is written whenever a span closes, carrying the span's
+    /// fields and the time it spent busy (entered) and idle (exited) as `time.busy` and `time.idle`
+    pub fn with_span_close_entries(mut self, span_close_entries: bool) -> Self {
+        self.span_close_entries = span_close_entries;
+        self
+    }
+
+    /// Configures the output format of entries: JSON for Cloud Logging (by default), or a
+    /// human-readable line per entry for local development (see [`Format`](crate::Format))
+    pub fn with_format(self, format: crate::Format) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.format = format;
+            event_formatter
+        })
+    }
+
+    /// Configures the human-readable [`Format::Pretty`](crate::Format::Pretty) output format with
+    /// colors, as a shorthand for `.with_format(Format::Pretty).with_ansi(true)`
+    pub fn pretty(self) -> Self {
+        self.with_format(crate::Format::Pretty).with_ansi(true)
+    }
+
+    /// Configures whether or not [`Format::Pretty`](crate::Format::Pretty) entries are colorized
+    /// with ANSI escape codes (JSON entries never are)
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.inner = self.inner.take().map(|inner| inner.with_ansi(ansi));
+        self.ansi = ansi;
+        self
     }
 
     /// Configures whether or not Events will include source locations in a special LogEntry field
     pub fn with_source_location(self, include_source_location: bool) -> Self {
-        Self(self.0.map_event_format(|mut event_formatter| {
+        self.map_event_format(|mut event_formatter| {
             event_formatter.include_source_location = include_source_location;
             event_formatter
-        }))
+        })
+    }
+
+    /// Configures how the keys of event fields, `http_request.*` fields, `labels.*` fields and span
+    /// fields are named (by default, span fields keep their recorded keys and all other keys are
+    /// converted to camelCase)
+    pub fn with_field_naming(self, field_naming: crate::FieldNaming) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_field_names = crate::naming::FieldNames::new(field_naming.clone());
+            event_formatter.field_names = crate::naming::FieldNames::new(field_naming);
+            event_formatter
+        })
+    }
+
+    /// Configures which Events include the `http_request.*` fields of their enclosing spans in
+    /// their `httpRequest` (all Events by default)
+    pub fn with_http_request_inheritance(self, inheritance: HttpRequestInheritance) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.http_request_inheritance = inheritance;
+            event_formatter
+        })
     }
 
-    /// Configures the Cloud Trace integration with OpenTelemetry through special LogEntry fields
-    #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
-    #[cfg(any(docsrs, feature = "opentelemetry"))]
+    /// Configures how the [`LogSeverity`](crate::LogSeverity) of Events is derived from their level,
+    /// target and fields (see [`SeverityMapper`](crate::SeverityMapper))
+    pub fn with_severity_mapper(self, severity_mapper: crate::SeverityMapper) -> Self {
+        let mut layer = self.map_event_format(|mut event_formatter| {
+            event_formatter.severity_mapper = severity_mapper.clone();
+            event_formatter
+        });
+
+        layer.severity_mapper = severity_mapper;
+        layer
+    }
+
+    /// Configures a [`Redaction`](crate::Redaction) stage for the fields of Events and spans,
+    /// which replaces sensitive values before they are serialized
+    #[cfg(feature = "redaction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "redaction")))]
+    pub fn with_redaction(self, redaction: crate::Redaction) -> Self {
+        let redaction = std::sync::Arc::new(redaction);
+        let mut layer = self.map_event_format(|mut event_formatter| {
+            event_formatter.redaction = Some(redaction.clone());
+            event_formatter
+        });
+
+        layer.redaction = Some(redaction);
+        layer
+    }
+
+    /// Configures a [`Sampling`](crate::Sampling) stage that suppresses Events through per-callsite
+    /// rate limits and per-severity probabilities, while keeping every Event in a sampled trace
+    pub fn with_sampling(mut self, sampling: crate::Sampling) -> Self {
+        self.sampler = Some(Sampler::new(sampling));
+        self
+    }
+
+    /// Configures consecutive identical Events from the same callsite (with the same message and
+    /// fields) to be collapsed: the first Event is written as usual, and its repeats are written as
+    /// a single entry with a `repeatCount` and `firstSeen`/`lastSeen` timestamps once a different
+    /// Event arrives, once no repeats have arrived within the timeout (as of the next Event or span
+    /// close), once their span closes, or once the Layer is dropped
+    pub fn with_deduplication(mut self, timeout: Duration) -> Self {
+        self.deduplicator = Some(Deduplicator::new(timeout));
+        self
+    }
+
+    /// Configures labels for every Event (e.g. the service name or version), which are overridden by
+    /// any `labels.*` fields of the same name on the Event or its spans. Their keys are renamed by
+    /// the configured [`FieldNaming`](crate::FieldNaming), like the keys of `labels.*` fields.
+    pub fn with_default_labels<K, V>(self, labels: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.default_labels.extend(
+                labels
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into())),
+            );
+            event_formatter
+        })
+    }
+
+    /// Configures fields for the top level of every Event's `jsonPayload` (e.g. a git SHA), which
+    /// are overridden by any Event fields of the same name. Their keys are renamed by the configured
+    /// [`FieldNaming`](crate::FieldNaming), like the keys of Event fields, and fields that would
+    /// collide with the fields written by the formatter itself (e.g. `severity`, `message` or any
+    /// `logging.googleapis.com/*` field) are left out.
+    pub fn with_static_fields<K, V>(self, fields: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<serde_json::Value>,
+    {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.static_fields.extend(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into())),
+            );
+            event_formatter
+        })
+    }
+
+    /// Configures a maximum size (in bytes) for serialized Events. Oversized Events are shrunk by
+    /// truncating the `message`, then large span fields, then dropping the `spans` list, then
+    /// shrinking the largest remaining fields, and are marked with `truncated` and `originalSize`
+    /// labels. Cloud Logging drops any entries larger
+    /// than [`MAX_ENTRY_SIZE`](crate::MAX_ENTRY_SIZE).
+    pub fn with_max_entry_size(self, max_entry_size: usize) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.max_entry_size = Some(max_entry_size);
+            event_formatter
+        })
+    }
+
+    /// Configures Events at `ERROR` severity or above that record an error (e.g. `error = &err as &dyn
+    /// Error`) to be picked up by [Cloud Error Reporting](https://cloud.google.com/error-reporting),
+    /// including the error's chain of sources as a `stack_trace` and the provided service context
+    pub fn with_error_reporting(self, service_context: crate::ServiceContext) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.service_context = Some(service_context);
+            event_formatter
+        })
+    }
+
+    /// Configures the Cloud Trace integration through special LogEntry fields, using either
+    /// OpenTelemetry or `trace_context` span fields (see [`CloudTraceContext`](crate::CloudTraceContext))
     pub fn with_cloud_trace(self, configuration: crate::CloudTraceConfiguration) -> Self {
-        Self(self.0.map_event_format(|mut event_formatter| {
+        self.map_event_format(|mut event_formatter| {
             event_formatter.cloud_trace_configuration = Some(configuration);
             event_formatter
-        }))
+        })
+    }
+
+    /// Configures the Cloud Trace integration from the Project ID of a monitored resource (e.g. one
+    /// found by a [`ResourceDetector`](crate::ResourceDetector)), unless a
+    /// [`CloudTraceConfiguration`](crate::CloudTraceConfiguration) has already been provided
+    pub fn with_monitored_resource(self, resource: &crate::MonitoredResource) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            if let (None, Some(project_id)) = (
+                event_formatter.cloud_trace_configuration.as_ref(),
+                resource.project_id(),
+            ) {
+                event_formatter.cloud_trace_configuration = Some(crate::CloudTraceConfiguration {
+                    project_id: project_id.to_string(),
+                });
+            }
+            event_formatter
+        })
+    }
+
+    fn map_event_format(mut self, map: impl FnOnce(EventFormatter) -> EventFormatter) -> Self {
+        self.inner = self.inner.take().map(|inner| inner.map_event_format(map));
+        self
     }
+
+    fn inner(&self) -> &tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W> {
+        self.inner
+            .as_ref()
+            .expect("The inner Layer should only be taken when the Layer is dropped")
+    }
+
+    #[cfg(feature = "redaction")]
+    fn redact_span_fields(&self, fields: &mut SpanFields) {
+        if let Some(redaction) = self.redaction.as_ref() {
+            redaction.redact_fields(&mut fields.0);
+        }
+    }
+
+    #[cfg(not(feature = "redaction"))]
+    fn redact_span_fields(&self, _fields: &mut SpanFields) {}
+}
+
+/// Policy for merging `http_request.*` fields recorded on spans into the `httpRequest` of the
+/// events within those spans
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpRequestInheritance {
+    /// Merge span fields into the `httpRequest` of every event
+    #[default]
+    Always,
+    /// Merge span fields only into events that record a `http_request.status`, e.g. the final
+    /// event of a request
+    WithStatus,
+    /// Build `httpRequest`s from event fields alone
+    Never,
 }
 
 /// Layer trait implementation that delegates to the inner Layer methods
@@ -81,13 +350,39 @@ where
     S: Subscriber + for<'span> LookupSpan<'span>,
     W: for<'writer> MakeWriter<'writer> + 'static,
 {
+    // Span fields are recorded as structured values for the EventFormatter rather than being
+    // formatted by the inner Layer
     fn on_new_span(
         &self,
         attrs: &tracing_core::span::Attributes<'_>,
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_new_span(attrs, id, context)
+        let Some(span) = context.span(id) else {
+            return;
+        };
+
+        let mut visitor = Visitor::default();
+        attrs.record(&mut visitor);
+
+        let mut fields = SpanFields(visitor.values);
+        let mut extensions = span.extensions_mut();
+
+        if let Some(trace_context) = fields.trace_context() {
+            extensions.replace(trace_context);
+        }
+
+        self.redact_span_fields(&mut fields);
+
+        if fields.0.contains_key("operation.id") {
+            extensions.replace(OperationState::default());
+        }
+
+        if self.span_close_entries {
+            extensions.replace(SpanTimings::new());
+        }
+
+        extensions.replace(fields);
     }
 
     fn on_record(
@@ -96,7 +391,32 @@ where
         values: &tracing_core::span::Record<'_>,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_record(span, values, context)
+        let Some(span) = context.span(span) else {
+            return;
+        };
+
+        let mut visitor = Visitor::default();
+        values.record(&mut visitor);
+
+        let mut recorded = SpanFields(visitor.values);
+        let mut extensions = span.extensions_mut();
+
+        if let Some(trace_context) = recorded.trace_context() {
+            extensions.replace(trace_context);
+        }
+
+        self.redact_span_fields(&mut recorded);
+
+        if recorded.0.contains_key("operation.id")
+            && extensions.get_mut::<OperationState>().is_none()
+        {
+            extensions.insert(OperationState::default());
+        }
+
+        match extensions.get_mut::<SpanFields>() {
+            Some(fields) => fields.0.extend(recorded.0),
+            None => extensions.insert(recorded),
+        }
     }
 
     fn on_enter(
@@ -104,7 +424,13 @@ where
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_enter(id, context)
+        if let (true, Some(span)) = (self.span_close_entries, context.span(id)) {
+            if let Some(timings) = span.extensions_mut().get_mut::<SpanTimings>() {
+                timings.enter();
+            }
+        }
+
+        self.inner().on_enter(id, context)
     }
 
     fn on_exit(
@@ -112,7 +438,13 @@ where
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_exit(id, context)
+        if let (true, Some(span)) = (self.span_close_entries, context.span(id)) {
+            if let Some(timings) = span.extensions_mut().get_mut::<SpanTimings>() {
+                timings.exit();
+            }
+        }
+
+        self.inner().on_exit(id, context)
     }
 
     fn on_close(
@@ -120,25 +452,129 @@ where
         id: tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_close(id, context)
+        // repeats within the closing span are written while the span can still be resolved
+        if let Some(deduplicator) = self.deduplicator.as_ref() {
+            deduplicator.flush_expired(|entry| self.inner().on_event(entry, context.clone()));
+            deduplicator.flush_span(&id, |entry| self.inner().on_event(entry, context.clone()));
+        }
+
+        if let Some(span) = context.span(&id) {
+            let (is_operation, timings) = {
+                let mut extensions = span.extensions_mut();
+                (
+                    extensions.get_mut::<OperationState>().is_some(),
+                    extensions.remove::<SpanTimings>(),
+                )
+            };
+
+            if let Some(timings) = timings {
+                span_events::with_close_entry(&id, span.metadata(), timings, |event| {
+                    self.inner().on_event(event, context.clone())
+                });
+            }
+
+            // operation spans finish with an entry marking the last entry of the operation
+            if is_operation {
+                operation::with_last_entry(&id, span.name(), |event| {
+                    self.inner().on_event(event, context.clone())
+                });
+            }
+        }
+
+        self.inner().on_close(id, context)
     }
 
     fn on_event(&self, event: &Event<'_>, context: tracing_subscriber::layer::Context<'_, S>) {
-        self.0.on_event(event, context)
+        if let Some(deduplicator) = self.deduplicator.as_ref() {
+            deduplicator.flush_expired(|entry| self.inner().on_event(entry, context.clone()));
+
+            let parent = context.event_span(event).map(|span| span.id());
+            let repeated = deduplicator.deduplicate(event, parent, |entry| {
+                self.inner().on_event(entry, context.clone())
+            });
+
+            if repeated {
+                return;
+            }
+        }
+
+        if let Some(sampler) = self.sampler.as_ref() {
+            let keep = sampler.sample(
+                event.metadata(),
+                || self.severity_mapper.event_severity(event),
+                || {
+                    context
+                        .event_span(event)
+                        .is_some_and(|span| sampling::is_trace_sampled(&span))
+                },
+                |summary| self.inner().on_event(summary, context.clone()),
+            );
+
+            if !keep {
+                return;
+            }
+        }
+
+        self.inner().on_event(event, context)
     }
 
     unsafe fn downcast_raw(&self, id: std::any::TypeId) -> Option<*const ()> {
-        self.0.downcast_raw(id)
+        self.inner().downcast_raw(id)
     }
 }
 
 impl<S, W> Deref for Layer<S, W>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + 'static,
 {
     type Target = tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.inner()
+    }
+}
+
+/// Entries that are still pending when the Layer is dropped (such as the repeats of the most recent
+/// Event, or summaries of suppressed Events) are written without any span context, since spans can no longer be resolved
+impl<S, W> Drop for Layer<S, W>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + 'static,
+{
+    fn drop(&mut self) {
+        let Some(inner) = self.inner.take() else {
+            return;
+        };
+
+        // the inner Layer only hands its event formatter back by replacing it
+        let mut event_formatter = None;
+        let inner = inner.map_event_format(|formatter| {
+            event_formatter = Some(formatter);
+            EventFormatter::default()
+        });
+        let Some(event_formatter) = event_formatter else {
+            return;
+        };
+
+        let write_entry = |event: &Event<'_>| {
+            let mut entry = String::new();
+
+            if event_formatter
+                .write_entry::<S>(None, None, &mut entry, self.ansi, event)
+                .is_ok()
+            {
+                let mut writer = inner.writer().make_writer_for(event.metadata());
+                let _ = io::Write::write_all(&mut writer, entry.as_bytes());
+            }
+        };
+
+        if let Some(deduplicator) = self.deduplicator.as_ref() {
+            deduplicator.flush(write_entry);
+        }
+
+        if let Some(sampler) = self.sampler.as_ref() {
+            sampler.flush(write_entry);
+        }
     }
 }
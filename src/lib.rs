@@ -5,25 +5,54 @@
 #![doc = include_str!("../README.md")]
 
 // Dummy uses to satisfy unused_crate_dependencies lint
-#[cfg(feature = "valuable")]
-use http as _;
 #[cfg(feature = "opentelemetry")]
 use opentelemetry as _;
 #[cfg(feature = "opentelemetry")]
 use tracing_opentelemetry as _;
 #[cfg(feature = "valuable")]
-use url as _;
-#[cfg(feature = "valuable")]
 use valuable as _;
 #[cfg(feature = "valuable")]
 use valuable_serde as _;
 
+mod dedup;
+pub mod entry;
+mod env;
 mod event_formatter;
+#[cfg(feature = "exporter")]
+mod exporter;
 mod google;
+mod internal_event;
 mod layer;
+#[cfg(feature = "tower")]
+mod middleware;
+mod naming;
+mod operation;
+mod panic;
+mod pretty;
+#[cfg(feature = "redaction")]
+mod redaction;
+mod resource;
+mod sampling;
 mod serializers;
+mod severity;
+mod span_events;
+mod truncation;
 mod visitor;
 mod writer;
 
+pub use self::env::EnvConfigError;
+#[cfg(feature = "exporter")]
+pub use self::exporter::*;
 pub use self::google::*;
 pub use self::layer::*;
+#[cfg(feature = "tower")]
+pub use self::middleware::{RequestLogging, RequestLoggingFuture, RequestLoggingLayer};
+pub use self::naming::FieldNaming;
+pub use self::panic::install_panic_hook;
+pub use self::pretty::Format;
+#[cfg(feature = "redaction")]
+pub use self::redaction::{HashKey, Redaction, Replacement};
+pub use self::resource::{ResourceDetectionError, ResourceDetector};
+pub use self::sampling::Sampling;
+pub use self::severity::SeverityMapper;
+pub use self::writer::{NonBlocking, NonBlockingBuilder, OverflowPolicy, WorkerGuard};
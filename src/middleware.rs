@@ -0,0 +1,141 @@
+use crate::google::HttpRequest;
+use http::{Request, Response};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tracing::{Level, Span};
+
+/// A tower Layer that logs a single entry with a populated `httpRequest` for every request handled
+/// by the wrapped Service. Requests are handled within a `request` span that carries any trace
+/// context propagated through `traceparent` or `X-Cloud-Trace-Context` headers, so entries can
+/// be correlated with [Cloud Trace](https://cloud.google.com/trace) (see
+/// [`with_cloud_trace`](crate::Layer::with_cloud_trace)).
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+#[derive(Clone, Debug, Default)]
+pub struct RequestLoggingLayer {
+    _private: (),
+}
+
+impl RequestLoggingLayer {
+    /// Create a Layer for logging requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> tower_layer::Layer<S> for RequestLoggingLayer {
+    type Service = RequestLogging<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestLogging { inner }
+    }
+}
+
+/// A tower Service that logs the requests handled by an inner Service (see [`RequestLoggingLayer`])
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+#[derive(Clone, Debug)]
+pub struct RequestLogging<S> {
+    inner: S,
+}
+
+impl<S, RequestBody, ResponseBody> tower_service::Service<Request<RequestBody>>
+    for RequestLogging<S>
+where
+    S: tower_service::Service<Request<RequestBody>, Response = Response<ResponseBody>>,
+    ResponseBody: http_body::Body,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RequestLoggingFuture<S::Future>;
+
+    fn poll_ready(&mut self, context: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(context)
+    }
+
+    fn call(&mut self, request: Request<RequestBody>) -> Self::Future {
+        let headers = request.headers();
+        let trace_context = headers
+            .get("traceparent")
+            .or_else(|| headers.get("x-cloud-trace-context"))
+            .and_then(|header| header.to_str().ok());
+        let span = tracing::info_span!("request", trace_context);
+        let http_request = HttpRequest::from(&request);
+        // started before the inner service is called, since it may do some of its work in `call`
+        let start = Instant::now();
+        let inner = span.in_scope(|| self.inner.call(request));
+
+        RequestLoggingFuture {
+            inner,
+            span,
+            http_request: Some(http_request),
+            start,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future of [`RequestLogging`]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+    pub struct RequestLoggingFuture<F> {
+        #[pin]
+        inner: F,
+        span: Span,
+        http_request: Option<HttpRequest>,
+        start: Instant,
+    }
+}
+
+impl<F, ResponseBody, E> Future for RequestLoggingFuture<F>
+where
+    F: Future<Output = Result<Response<ResponseBody>, E>>,
+    ResponseBody: http_body::Body,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _guard = this.span.enter();
+        let result = ready!(this.inner.poll(context));
+
+        if let Some(http_request) = this.http_request.take() {
+            let mut http_request = http_request.with_latency(this.start.elapsed());
+
+            if let Ok(response) = &result {
+                http_request.response_size = http_body::Body::size_hint(response.body())
+                    .exact()
+                    .and_then(|size| size.try_into().ok());
+                http_request = http_request.with_response(response);
+            }
+
+            log(http_request);
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+/// Emit an event at a severity derived from the response status
+macro_rules! log_request {
+    ($level:expr, $http_request:expr) => {
+        tracing::event!(
+            $level,
+            http_request = %$http_request,
+            "{} {}",
+            $http_request.request_method.as_ref().map_or("", |method| method.as_str()),
+            $http_request.request_url.as_ref().map_or("", |url| url.as_str()),
+        )
+    };
+}
+
+fn log(http_request: HttpRequest) {
+    match http_request.status {
+        Some(status) if status.is_server_error() => log_request!(Level::ERROR, http_request),
+        Some(status) if status.is_client_error() => log_request!(Level::WARN, http_request),
+        Some(_) => log_request!(Level::INFO, http_request),
+        None => log_request!(Level::ERROR, http_request),
+    }
+}
@@ -0,0 +1,150 @@
+use inflector::Inflector;
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Deref,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+/// Upper bound on the number of cached field names, protecting against unbounded growth from
+/// dynamically-generated keys
+const MAX_CACHED_NAMES: usize = 4096;
+
+type NamingFunction = dyn Fn(&str) -> String + Send + Sync;
+
+/// Policy for re-writing the keys of event fields, `http_request.*` fields, `labels.*` fields
+/// and span fields in formatted LogEntries
+///
+/// Without a configured policy, span fields keep the keys they were recorded with and every other
+/// key is converted to camelCase. A configured policy applies to span fields as well.
+#[derive(Clone, Default)]
+pub enum FieldNaming {
+    /// Convert keys to camelCase (e.g. `field_name` -> `fieldName`, or `field.name` -> `fieldName`)
+    #[default]
+    CamelCase,
+    /// Convert keys to snake_case (e.g. `fieldName` -> `field_name`, or `field.name` -> `field_name`)
+    SnakeCase,
+    /// Leave keys exactly as they were recorded
+    Preserve,
+    /// Convert keys with a custom function
+    Custom(Arc<NamingFunction>),
+}
+
+impl FieldNaming {
+    /// Create a policy that converts keys with a custom function
+    pub fn custom<F>(naming: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(naming))
+    }
+
+    fn convert(&self, name: &str) -> String {
+        match self {
+            Self::CamelCase => name.to_camel_case(),
+            Self::SnakeCase => name.to_snake_case(),
+            Self::Preserve => name.to_string(),
+            Self::Custom(naming) => naming(name),
+        }
+    }
+}
+
+impl fmt::Debug for FieldNaming {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CamelCase => formatter.write_str("CamelCase"),
+            Self::SnakeCase => formatter.write_str("SnakeCase"),
+            Self::Preserve => formatter.write_str("Preserve"),
+            Self::Custom(_) => formatter.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// A [`FieldNaming`] policy that caches conversions by field name, since the same handful of
+/// field names are converted on every event
+#[derive(Debug, Default)]
+pub(crate) struct FieldNames {
+    naming: FieldNaming,
+    cache: RwLock<HashMap<String, Arc<str>>>,
+}
+
+impl FieldNames {
+    pub(crate) fn new(naming: FieldNaming) -> Self {
+        Self {
+            naming,
+            cache: RwLock::default(),
+        }
+    }
+
+    /// Convert a field name according to the naming policy, sharing cached conversions
+    /// instead of allocating a new name for every call
+    pub(crate) fn convert<'a>(&self, name: &'a str) -> FieldName<'a> {
+        if let FieldNaming::Preserve = self.naming {
+            return FieldName::Borrowed(name);
+        }
+
+        let cache = self.cache.read().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(converted) = cache.get(name) {
+            return FieldName::Shared(Arc::clone(converted));
+        }
+
+        drop(cache);
+
+        let converted: Arc<str> = self.naming.convert(name).into();
+        let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+
+        if cache.len() < MAX_CACHED_NAMES {
+            cache.insert(name.to_string(), Arc::clone(&converted));
+        }
+
+        FieldName::Shared(converted)
+    }
+}
+
+/// A field name converted by [`FieldNames`], either borrowed from the recorded name or shared
+/// with the cache of conversions
+#[derive(Clone, Debug)]
+pub(crate) enum FieldName<'a> {
+    Borrowed(&'a str),
+    Shared(Arc<str>),
+}
+
+impl FieldName<'_> {
+    pub(crate) fn into_owned(self) -> String {
+        self.to_string()
+    }
+}
+
+impl Deref for FieldName<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Borrowed(name) => name,
+            Self::Shared(name) => name,
+        }
+    }
+}
+
+impl AsRef<str> for FieldName<'_> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Display for FieldName<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self)
+    }
+}
+
+impl Serialize for FieldName<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal_event::{internal_callsite, with_internal_event};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_core::{span::Id, Event};
+
+internal_callsite!(
+    LAST_ENTRY_CALLSITE,
+    "operation",
+    &["message", "operation.last"]
+);
+
+/// Span extension for spans that record an `operation.id`, tracking whether the operation's first
+/// entry has been written yet
+#[derive(Debug, Default)]
+pub(crate) struct OperationState {
+    started: AtomicBool,
+}
+
+impl OperationState {
+    /// Mark the operation as started, returning whether this is its first entry
+    pub(crate) fn start(&self) -> bool {
+        !self.started.swap(true, Ordering::Relaxed)
+    }
+}
+
+/// Build the closing entry of an operation span (named by the span) and hand it to a callback
+pub(crate) fn with_last_entry(span: &Id, name: &str, callback: impl FnOnce(&Event<'_>)) {
+    with_internal_event(&LAST_ENTRY_CALLSITE, Some(span), [&name, &true], callback);
+}
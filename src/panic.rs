@@ -0,0 +1,52 @@
+use std::{backtrace::Backtrace, panic::PanicHookInfo, sync::Once};
+
+/// Installs a panic hook that logs panics as `CRITICAL` Events, including the panic's location as
+/// the LogEntry's source location and a backtrace as a `stack_trace` that
+/// [Cloud Error Reporting](https://cloud.google.com/error-reporting) understands. Because the hook
+/// runs on the panicking thread, Events are emitted within the current span context.
+///
+/// The previously installed hook (e.g. the default hook, which prints panics to stderr) still runs
+/// after every panic is logged. Installing the hook more than once has no further effect.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            log_panic(info);
+            previous(info);
+        }));
+    });
+}
+
+fn log_panic(info: &PanicHookInfo<'_>) {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    // Error Reporting expects stack traces to start with the error message
+    let stack_trace = format!("{message}\n{}", Backtrace::force_capture());
+
+    match info.location() {
+        Some(location) => tracing::error!(
+            target: "panic",
+            severity = "critical",
+            source_location.file = location.file(),
+            source_location.line = location.line(),
+            stack_trace,
+            "{}",
+            message
+        ),
+        None => tracing::error!(
+            target: "panic",
+            severity = "critical",
+            stack_trace,
+            "{}",
+            message
+        ),
+    }
+}
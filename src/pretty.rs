@@ -0,0 +1,167 @@
+use std::fmt::{self, Write};
+
+type Entry = serde_json::Map<String, serde_json::Value>;
+
+/// Prefix of LogEntry special fields, which is left out of their pretty names
+const SPECIAL_FIELD_PREFIX: &str = "logging.googleapis.com/";
+
+/// Output format of a Stackdriver Layer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line, as picked up by Cloud Logging agents
+    #[default]
+    Json,
+    /// One human-readable line per entry for local development, rendered from the same fields as
+    /// the JSON format (and colorized with [`Layer::with_ansi`](crate::Layer::with_ansi))
+    Pretty,
+}
+
+/// Render a serialized JSON entry as a single human-readable line:
+/// `<time> <SEVERITY> <target> [<span> > <span>] <message> <fields...> at <file>:<line>`
+pub(crate) fn write_pretty(writer: &mut impl Write, entry: &[u8], ansi: bool) -> fmt::Result {
+    let Ok(mut entry) = serde_json::from_slice::<Entry>(entry) else {
+        return writer.write_str(&String::from_utf8_lossy(entry));
+    };
+
+    let style = Style(ansi);
+
+    if let Some(time) = entry.remove("time") {
+        style.paint(writer, DIMMED, display_str(&time))?;
+        writer.write_char(' ')?;
+    }
+
+    let severity = entry
+        .remove("severity")
+        .map(|severity| display_str(&severity).into_owned())
+        .unwrap_or_default();
+    style.paint(
+        writer,
+        severity_color(&severity),
+        format_args!("{severity:>9}"),
+    )?;
+
+    if let Some(target) = entry.remove("target") {
+        writer.write_char(' ')?;
+        style.paint(writer, BOLD, display_str(&target))?;
+    }
+
+    // the list of spans describes the current span scope, while the single span describes the
+    // (possibly explicit) parent of an entry outside of the current scope
+    let spans = entry.remove("spans");
+    let span = entry.remove("span");
+    let breadcrumb = match (spans, span) {
+        (Some(serde_json::Value::Array(spans)), _) => spans
+            .iter()
+            .filter_map(|span| span.get("name").map(display_str))
+            .collect::<Vec<_>>()
+            .join(" > "),
+        (_, Some(span)) => span
+            .get("name")
+            .map(display_str)
+            .unwrap_or_default()
+            .into_owned(),
+        _ => String::new(),
+    };
+
+    if !breadcrumb.is_empty() {
+        writer.write_char(' ')?;
+        style.paint(writer, DIMMED, format_args!("[{breadcrumb}]"))?;
+    }
+
+    if let Some(message) = entry.remove("message") {
+        write!(writer, " {}", display_str(&message))?;
+    }
+
+    let source_location = entry.remove(&format!("{SPECIAL_FIELD_PREFIX}sourceLocation"));
+
+    // event fields come first, followed by special fields without their common prefix
+    let (special, fields): (Vec<_>, Vec<_>) = entry
+        .iter()
+        .partition(|(key, _)| key.starts_with(SPECIAL_FIELD_PREFIX));
+
+    for (key, value) in fields.into_iter().chain(special) {
+        writer.write_char(' ')?;
+        write_field(
+            writer,
+            &style,
+            key.strip_prefix(SPECIAL_FIELD_PREFIX).unwrap_or(key),
+            value,
+        )?;
+    }
+
+    if let Some(source_location) = source_location {
+        let file = source_location.get("file").map(display_str);
+        let line = source_location.get("line").map(display_str);
+
+        if let Some(file) = file {
+            writer.write_char(' ')?;
+            match line {
+                Some(line) => style.paint(writer, DIMMED, format_args!("at {file}:{line}"))?,
+                None => style.paint(writer, DIMMED, format_args!("at {file}"))?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a field as `key=value`, or as `key{key=value ...}` for nested objects
+fn write_field(
+    writer: &mut impl Write,
+    style: &Style,
+    key: &str,
+    value: &serde_json::Value,
+) -> fmt::Result {
+    style.paint(writer, ITALIC, key)?;
+
+    match value {
+        serde_json::Value::Object(fields) => {
+            writer.write_char('{')?;
+            for (index, (key, value)) in fields.iter().enumerate() {
+                if index > 0 {
+                    writer.write_char(' ')?;
+                }
+                write_field(writer, style, key, value)?;
+            }
+            writer.write_char('}')
+        }
+        value => write!(writer, "={value}"),
+    }
+}
+
+/// Display strings without their JSON quotes, and any other value as JSON
+fn display_str(value: &serde_json::Value) -> std::borrow::Cow<'_, str> {
+    match value {
+        serde_json::Value::String(value) => value.into(),
+        value => value.to_string().into(),
+    }
+}
+
+const BOLD: &str = "1";
+const DIMMED: &str = "2";
+const ITALIC: &str = "3";
+
+/// ANSI color of a LogSeverity's name
+fn severity_color(severity: &str) -> &'static str {
+    match severity {
+        "DEBUG" => "34",
+        "INFO" => "32",
+        "NOTICE" => "36",
+        "WARNING" => "33",
+        "ERROR" => "31",
+        "CRITICAL" | "ALERT" | "EMERGENCY" => "1;31",
+        _ => "0",
+    }
+}
+
+/// Whether or not to write ANSI escape codes
+struct Style(bool);
+
+impl Style {
+    fn paint(&self, writer: &mut impl Write, code: &str, text: impl fmt::Display) -> fmt::Result {
+        match self.0 {
+            true => write!(writer, "\x1b[{code}m{text}\x1b[0m"),
+            false => write!(writer, "{text}"),
+        }
+    }
+}
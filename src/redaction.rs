@@ -0,0 +1,242 @@
+use regex::Regex;
+use ring::hmac;
+use std::{borrow::Cow, fmt};
+
+type Fields = serde_json::Map<String, serde_json::Value>;
+
+/// How redacted values are replaced
+#[cfg_attr(docsrs, doc(cfg(feature = "redaction")))]
+#[derive(Clone, Debug, Default)]
+pub enum Replacement {
+    /// Replace values with `[REDACTED]`
+    #[default]
+    Redacted,
+    /// Replace values with a keyed hash of the original (e.g. `[HASH:5f2b8c1a9e3d7f60]`), so that
+    /// entries with equal values can still be correlated (see [`Replacement::hash`])
+    Hash(HashKey),
+}
+
+impl Replacement {
+    /// Replace values with the first 64 bits of their HMAC-SHA256 under a secret key. Hashes are
+    /// stable across processes and releases for the same key, and can't be reversed by hashing
+    /// guessed values (e.g. email addresses) without it. Keep the key as secret as the values
+    /// themselves, and use a long random key (e.g. 32 random bytes).
+    pub fn hash(secret: impl AsRef<[u8]>) -> Self {
+        Self::Hash(HashKey(hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref())))
+    }
+}
+
+/// The secret key of a [`Replacement::Hash`], which is never printed
+#[cfg_attr(docsrs, doc(cfg(feature = "redaction")))]
+#[derive(Clone)]
+pub struct HashKey(hmac::Key);
+
+impl fmt::Debug for HashKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("HashKey(..)")
+    }
+}
+
+/// A redaction stage that replaces sensitive fields and values in event fields (including labels
+/// and nested `valuable` structures) and span fields before they are serialized
+#[cfg_attr(docsrs, doc(cfg(feature = "redaction")))]
+#[derive(Clone, Debug, Default)]
+pub struct Redaction {
+    field_patterns: Vec<String>,
+    scrubbers: Vec<Regex>,
+    replacement: Replacement,
+}
+
+impl Redaction {
+    /// Create a redaction stage that leaves every field untouched
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redact the whole value of fields with names matching a glob pattern, where `*` matches any
+    /// number of characters and `?` matches a single character (e.g. `*password*`). Patterns are
+    /// matched against both the full dotted path of a field as it was recorded (e.g.
+    /// `http_request.request_url`, or `user.email` for a field of a nested value) and its own name
+    /// (e.g. `email`). Query parameters with matching names are redacted from URL values as well.
+    pub fn with_field_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.field_patterns.push(pattern.into());
+        self
+    }
+
+    /// Redact any matches of a regular expression within string values (e.g. email addresses
+    /// or card numbers)
+    pub fn with_scrubber(mut self, scrubber: Regex) -> Self {
+        self.scrubbers.push(scrubber);
+        self
+    }
+
+    /// Configure how redacted values are replaced (`[REDACTED]` by default)
+    pub fn with_replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Redact a set of recorded fields in place
+    pub(crate) fn redact_fields(&self, fields: &mut Fields) {
+        for (key, value) in fields.iter_mut() {
+            // whole `http_request`s recorded through their `Display` implementation are redacted
+            // field-by-field, like the structured values they represent
+            if let ("http_request", serde_json::Value::String(request)) = (key.as_str(), &*value) {
+                if let Ok(request @ serde_json::Value::Object(_)) = serde_json::from_str(request) {
+                    *value = request;
+                }
+            }
+
+            self.redact(key, key.rsplit('.').next().unwrap_or(key), value);
+        }
+    }
+
+    /// Scrub a string value that isn't associated with any field (e.g. an error chain)
+    pub(crate) fn redact_str<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        self.scrub(value)
+            .map(Cow::Owned)
+            .unwrap_or(Cow::Borrowed(value))
+    }
+
+    fn redact(&self, path: &str, name: &str, value: &mut serde_json::Value) {
+        if self.is_denied(path) || self.is_denied(name) {
+            let replacement = match &*value {
+                serde_json::Value::String(value) => self.replace(value),
+                value => self.replace(&value.to_string()),
+            };
+            *value = serde_json::Value::String(replacement);
+            return;
+        }
+
+        match value {
+            serde_json::Value::String(value) => {
+                if let Some(scrubbed) = self.scrub(value) {
+                    *value = scrubbed;
+                }
+            }
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    self.redact(path, name, value);
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for (key, value) in fields.iter_mut() {
+                    self.redact(&format!("{path}.{key}"), key, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_denied(&self, name: &str) -> bool {
+        self.field_patterns
+            .iter()
+            .any(|pattern| matches_glob(pattern.as_bytes(), name.as_bytes()))
+    }
+
+    fn replace(&self, value: &str) -> String {
+        match &self.replacement {
+            Replacement::Redacted => "[REDACTED]".to_string(),
+            Replacement::Hash(HashKey(key)) => {
+                let tag = hmac::sign(key, value.as_bytes());
+                let hash = tag.as_ref()[..8]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<String>();
+
+                format!("[HASH:{hash}]")
+            }
+        }
+    }
+
+    /// Redact denied query parameters and scrubber matches, returning `None` if nothing changed
+    fn scrub(&self, value: &str) -> Option<String> {
+        let mut scrubbed = match self.redact_query(value) {
+            Some(redacted) => Cow::Owned(redacted),
+            None => Cow::Borrowed(value),
+        };
+
+        for scrubber in &self.scrubbers {
+            let replaced = match scrubber.replace_all(&scrubbed, |captures: &regex::Captures| {
+                self.replace(&captures[0])
+            }) {
+                Cow::Owned(replaced) => Some(replaced),
+                Cow::Borrowed(_) => None,
+            };
+
+            if let Some(replaced) = replaced {
+                scrubbed = Cow::Owned(replaced);
+            }
+        }
+
+        match scrubbed {
+            Cow::Owned(scrubbed) => Some(scrubbed),
+            Cow::Borrowed(_) => None,
+        }
+    }
+
+    /// Redact the values of denied query parameters in URL-like values
+    fn redact_query(&self, url: &str) -> Option<String> {
+        if self.field_patterns.is_empty() || url.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let (base, rest) = url.split_once('?')?;
+        let (query, fragment) = match rest.split_once('#') {
+            Some((query, fragment)) => (query, Some(fragment)),
+            None => (rest, None),
+        };
+
+        let mut redacted = false;
+        let query = query
+            .split('&')
+            .map(|parameter| match parameter.split_once('=') {
+                Some((name, value)) if self.is_denied(name) => {
+                    redacted = true;
+                    format!("{name}={}", self.replace(value))
+                }
+                _ => parameter.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+
+        if !redacted {
+            return None;
+        }
+
+        Some(match fragment {
+            Some(fragment) => format!("{base}?{query}#{fragment}"),
+            None => format!("{base}?{query}"),
+        })
+    }
+}
+
+/// Match a name against a glob pattern with `*` and `?` wildcards
+fn matches_glob(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut pattern_index, mut name_index) = (0, 0);
+    let mut backtrack = None;
+
+    while name_index < name.len() {
+        match pattern.get(pattern_index) {
+            Some(b'*') => {
+                backtrack = Some((pattern_index, name_index));
+                pattern_index += 1;
+            }
+            Some(&byte) if byte == b'?' || byte == name[name_index] => {
+                pattern_index += 1;
+                name_index += 1;
+            }
+            _ => match backtrack {
+                // let the last wildcard consume one more character and try again
+                Some((wildcard_index, wildcard_name_index)) => {
+                    pattern_index = wildcard_index + 1;
+                    name_index = wildcard_name_index + 1;
+                    backtrack = Some((wildcard_index, name_index));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|byte| *byte == b'*')
+}
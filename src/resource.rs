@@ -0,0 +1,268 @@
+use crate::google::MonitoredResource;
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+const DEFAULT_METADATA_HOST: &str = "metadata.google.internal";
+// the well-known link-local address of the metadata server, which needs no DNS lookup
+const DEFAULT_METADATA_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::new(169, 254, 169, 254));
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+const KUBERNETES_NAMESPACE_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Detects the [`MonitoredResource`] that the current process is running as from environment
+/// variables and the [metadata server](https://cloud.google.com/compute/docs/metadata/overview),
+/// recognizing Cloud Run revisions, Cloud Functions, GKE containers and GCE instances.
+///
+/// The metadata server address defaults to the `GCE_METADATA_HOST` environment variable (or
+/// `metadata.google.internal`), and can be overridden with
+/// [`with_metadata_host`](ResourceDetector::with_metadata_host).
+///
+/// Detection only runs when [`detect`](ResourceDetector::detect) is called, since it blocks on
+/// requests to the metadata server. The detected resource is then handed to both the Layer (with
+/// [`with_monitored_resource`](crate::Layer::with_monitored_resource)) and any
+/// `CloudLoggingExporter` (with `CloudLoggingExporter::from_resource`).
+#[derive(Clone, Debug)]
+pub struct ResourceDetector {
+    metadata_host: String,
+    timeout: Duration,
+}
+
+impl Default for ResourceDetector {
+    fn default() -> Self {
+        Self {
+            metadata_host: env::var("GCE_METADATA_HOST")
+                .unwrap_or_else(|_| DEFAULT_METADATA_HOST.to_string()),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl ResourceDetector {
+    /// Create a detector that uses the default metadata server
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `host[:port]` of the metadata server (e.g. a local stand-in)
+    pub fn with_metadata_host(self, metadata_host: impl Into<String>) -> Self {
+        Self {
+            metadata_host: metadata_host.into(),
+            ..self
+        }
+    }
+
+    /// Sets how long each request to the metadata server (including any DNS lookup of its host) may
+    /// take before it's abandoned
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Detect the monitored resource of the current process, falling back to a `global`
+    /// resource when no specific platform is recognized. Fails if no Project ID can be found
+    /// in `GOOGLE_CLOUD_PROJECT` or the metadata server. Each request to the metadata server
+    /// blocks for up to the configured timeout.
+    pub fn detect(&self) -> Result<MonitoredResource, ResourceDetectionError> {
+        let project_id = env_var("GOOGLE_CLOUD_PROJECT")
+            .or_else(|| self.metadata("project/project-id"))
+            .ok_or(ResourceDetectionError)?;
+
+        let resource = if env_var("FUNCTION_TARGET").is_some() {
+            self.cloud_function()
+        } else if let (Some(service), Some(revision)) =
+            (env_var("K_SERVICE"), env_var("K_REVISION"))
+        {
+            self.cloud_run_revision(service, revision)
+        } else if env_var("KUBERNETES_SERVICE_HOST").is_some() {
+            self.k8s_container()
+        } else {
+            self.gce_instance()
+        };
+
+        let mut resource = resource.unwrap_or_else(|| MonitoredResource::global(""));
+        resource.labels.insert("project_id".to_string(), project_id);
+
+        Ok(resource)
+    }
+
+    fn cloud_function(&self) -> Option<MonitoredResource> {
+        let function_name = env_var("K_SERVICE").or_else(|| env_var("FUNCTION_NAME"))?;
+
+        Some(resource(
+            "cloud_function",
+            [
+                ("function_name", Some(function_name)),
+                ("region", self.region()),
+            ],
+        ))
+    }
+
+    fn cloud_run_revision(&self, service: String, revision: String) -> Option<MonitoredResource> {
+        Some(resource(
+            "cloud_run_revision",
+            [
+                ("service_name", Some(service)),
+                ("revision_name", Some(revision)),
+                ("configuration_name", env_var("K_CONFIGURATION")),
+                ("location", self.region()),
+            ],
+        ))
+    }
+
+    fn k8s_container(&self) -> Option<MonitoredResource> {
+        let namespace = env_var("NAMESPACE").or_else(|| {
+            std::fs::read_to_string(KUBERNETES_NAMESPACE_FILE)
+                .ok()
+                .map(|namespace| namespace.trim().to_string())
+        });
+
+        Some(resource(
+            "k8s_container",
+            [
+                (
+                    "location",
+                    self.metadata("instance/attributes/cluster-location"),
+                ),
+                (
+                    "cluster_name",
+                    self.metadata("instance/attributes/cluster-name"),
+                ),
+                ("namespace_name", namespace),
+                (
+                    "pod_name",
+                    env_var("POD_NAME").or_else(|| env_var("HOSTNAME")),
+                ),
+                ("container_name", env_var("CONTAINER_NAME")),
+            ],
+        ))
+    }
+
+    fn gce_instance(&self) -> Option<MonitoredResource> {
+        let instance_id = self.metadata("instance/id")?;
+
+        Some(resource(
+            "gce_instance",
+            [
+                ("instance_id", Some(instance_id)),
+                ("zone", self.metadata("instance/zone").map(last_segment)),
+            ],
+        ))
+    }
+
+    /// Cloud Run and Cloud Functions expose their region as `projects/{number}/regions/{region}`
+    fn region(&self) -> Option<String> {
+        self.metadata("instance/region").map(last_segment)
+    }
+
+    /// Fetch a single value from the metadata server, treating any failure as a missing value
+    fn metadata(&self, path: &str) -> Option<String> {
+        self.fetch_metadata(path)
+            .ok()
+            .flatten()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn fetch_metadata(&self, path: &str) -> io::Result<Option<String>> {
+        let address = self.resolve()?;
+
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        // HTTP/1.0 keeps responses un-chunked and closes the connection once the body is sent
+        write!(
+            stream,
+            "GET /computeMetadata/v1/{path} HTTP/1.0\r\nHost: {}\r\nMetadata-Flavor: Google\r\n\r\n",
+            self.metadata_host
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        let Some((head, body)) = response.split_once("\r\n\r\n") else {
+            return Ok(None);
+        };
+
+        let is_ok = head
+            .lines()
+            .next()
+            .and_then(|status| status.split_whitespace().nth(1))
+            == Some("200");
+
+        Ok(is_ok.then(|| body.to_string()))
+    }
+
+    /// Resolve the address of the metadata server, without letting a DNS lookup (e.g. of
+    /// `metadata.google.internal` outside of Google Cloud) outlast the timeout
+    fn resolve(&self) -> io::Result<SocketAddr> {
+        if let Ok(address) = self.metadata_host.parse() {
+            return Ok(address);
+        }
+
+        if let Ok(ip) = self.metadata_host.parse() {
+            return Ok(SocketAddr::new(ip, 80));
+        }
+
+        let (host, port) = match self.metadata_host.rsplit_once(':') {
+            Some((host, port)) => (
+                host.to_string(),
+                port.parse()
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?,
+            ),
+            None => (self.metadata_host.clone(), 80),
+        };
+
+        if host == DEFAULT_METADATA_HOST {
+            return Ok(SocketAddr::new(DEFAULT_METADATA_ADDRESS, port));
+        }
+
+        // blocking lookups can't be cancelled, so they finish on their own thread in the background
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let address = (host.as_str(), port)
+                .to_socket_addrs()
+                .map(|mut addresses| addresses.next());
+            let _ = sender.send(address);
+        });
+
+        receiver
+            .recv_timeout(self.timeout)
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??
+            .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))
+    }
+}
+
+/// Error returned when a [`ResourceDetector`] cannot determine the current Project ID
+#[derive(Debug, thiserror::Error)]
+#[error("Unable to detect a Project ID from GOOGLE_CLOUD_PROJECT or the metadata server")]
+pub struct ResourceDetectionError;
+
+fn resource<const N: usize>(
+    r#type: &str,
+    labels: [(&str, Option<String>); N],
+) -> MonitoredResource {
+    MonitoredResource {
+        r#type: r#type.to_string(),
+        labels: labels
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect::<BTreeMap<_, _>>(),
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn last_segment(value: String) -> String {
+    match value.rsplit_once('/') {
+        Some((_, segment)) => segment.to_string(),
+        None => value,
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    google::{CloudTraceContext, LogSeverity},
+    internal_event::{internal_callsite, with_internal_event},
+};
+#[cfg(feature = "opentelemetry")]
+use opentelemetry::trace::{SamplingDecision, TraceContextExt};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+use tracing_core::{callsite::Identifier, Event, Metadata};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+internal_callsite!(
+    SUMMARY_CALLSITE,
+    "sampling",
+    &[
+        "message",
+        "severity",
+        "source_location.file",
+        "source_location.line",
+        "sampling.target",
+        "sampling.suppressed",
+    ]
+);
+
+/// Configuration for sampling Events before they are written, through per-callsite rate limits
+/// and per-severity probabilities. Events within sampled traces (see
+/// [`with_cloud_trace`](crate::Layer::with_cloud_trace)) are always kept, and the number of
+/// suppressed Events is written in a summary entry for each callsite alongside the first Event
+/// after every summary interval (and when the Layer is dropped).
+#[derive(Clone, Debug)]
+pub struct Sampling {
+    rate_limit: Option<(f64, u32)>,
+    probabilities: Vec<(LogSeverity, f64)>,
+    summary_interval: Duration,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            rate_limit: None,
+            probabilities: vec![],
+            summary_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Sampling {
+    /// Create a sampling configuration that keeps every Event
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit every callsite to bursts of up to `burst` Events, replenished at a rate of
+    /// `per_second` Events per second
+    pub fn with_rate_limit(mut self, per_second: f64, burst: u32) -> Self {
+        self.rate_limit = Some((per_second.max(0.0), burst));
+        self
+    }
+
+    /// Keep Events at a severity with a probability between `0.0` (drop every Event) and `1.0`
+    /// (keep every Event). Severities are resolved as they are written, including any `severity`
+    /// field and the configured [`SeverityMapper`](crate::SeverityMapper).
+    pub fn with_probability(mut self, severity: LogSeverity, probability: f64) -> Self {
+        self.probabilities
+            .retain(|(rule_severity, _)| *rule_severity != severity);
+        self.probabilities
+            .push((severity, probability.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Configure the minimum interval between summaries of suppressed Events (one minute by
+    /// default). Summaries are written alongside the first Event after the interval elapses, so
+    /// they are only as periodic as the Events that arrive; any pending summaries are also written
+    /// when the Layer is dropped.
+    pub fn with_summary_interval(mut self, summary_interval: Duration) -> Self {
+        self.summary_interval = summary_interval;
+        self
+    }
+}
+
+/// Sampling state of a single callsite
+struct CallsiteState {
+    metadata: &'static Metadata<'static>,
+    tokens: f64,
+    refilled: Instant,
+    suppressed: u64,
+}
+
+struct SamplerState {
+    callsites: HashMap<Identifier, CallsiteState>,
+    summarized: Instant,
+}
+
+/// The sampling stage of a Layer, applying a [`Sampling`] configuration
+pub(crate) struct Sampler {
+    sampling: Sampling,
+    state: Mutex<SamplerState>,
+    random: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) fn new(sampling: Sampling) -> Self {
+        Self {
+            sampling,
+            state: Mutex::new(SamplerState {
+                callsites: HashMap::new(),
+                summarized: Instant::now(),
+            }),
+            random: AtomicU64::new(RandomState::new().hash_one(0)),
+        }
+    }
+
+    /// Decide whether or not to keep an Event, resolving its severity only when probabilities are
+    /// configured, and checking whether its trace was sampled only once the Event would otherwise
+    /// be suppressed. Summaries of suppressed Events that are due are handed to a callback.
+    pub(crate) fn sample(
+        &self,
+        metadata: &'static Metadata<'static>,
+        severity: impl FnOnce() -> LogSeverity,
+        trace_sampled: impl FnOnce() -> bool,
+        mut summarize: impl FnMut(&Event<'_>),
+    ) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let callsite = state
+            .callsites
+            .entry(metadata.callsite())
+            .or_insert_with(|| CallsiteState {
+                metadata,
+                tokens: self
+                    .sampling
+                    .rate_limit
+                    .map_or(0.0, |(_, burst)| burst.into()),
+                refilled: now,
+                suppressed: 0,
+            });
+
+        let mut keep = match self.sampling.probabilities.is_empty() {
+            true => true,
+            false => {
+                let severity = severity();
+                self.sampling
+                    .probabilities
+                    .iter()
+                    .find(|(rule_severity, _)| *rule_severity == severity)
+                    .is_none_or(|(_, probability)| self.random() < *probability)
+            }
+        };
+
+        if let (true, Some((per_second, burst))) = (keep, self.sampling.rate_limit) {
+            let elapsed = now.saturating_duration_since(callsite.refilled);
+            callsite.tokens =
+                (callsite.tokens + elapsed.as_secs_f64() * per_second).min(burst.into());
+            callsite.refilled = now;
+
+            if callsite.tokens >= 1.0 {
+                callsite.tokens -= 1.0;
+            } else {
+                keep = false;
+            }
+        }
+
+        if !keep && !trace_sampled() {
+            callsite.suppressed += 1;
+        } else {
+            keep = true;
+        }
+
+        let mut summaries = vec![];
+
+        if now.saturating_duration_since(state.summarized) >= self.sampling.summary_interval {
+            state.summarized = now;
+            summaries = Self::take_summaries(&mut state);
+        }
+
+        // summaries are written without holding the lock, in case writing them emits more Events
+        drop(state);
+
+        for (metadata, suppressed) in summaries {
+            with_summary(metadata, suppressed, &mut summarize);
+        }
+
+        keep
+    }
+
+    /// Hand summaries of every callsite with suppressed Events to a callback, regardless of the
+    /// summary interval
+    pub(crate) fn flush(&self, mut summarize: impl FnMut(&Event<'_>)) {
+        let summaries = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.summarized = Instant::now();
+            Self::take_summaries(&mut state)
+        };
+
+        for (metadata, suppressed) in summaries {
+            with_summary(metadata, suppressed, &mut summarize);
+        }
+    }
+
+    /// Take the number of suppressed Events of every callsite that has any
+    fn take_summaries(state: &mut SamplerState) -> Vec<(&'static Metadata<'static>, u64)> {
+        state
+            .callsites
+            .values_mut()
+            .filter(|callsite| callsite.suppressed > 0)
+            .map(|callsite| (callsite.metadata, std::mem::take(&mut callsite.suppressed)))
+            .collect()
+    }
+
+    /// A uniformly-distributed number between 0 and 1 (from a SplitMix64 sequence)
+    fn random(&self) -> f64 {
+        let mut random = self
+            .random
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        random = (random ^ (random >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        random = (random ^ (random >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        random ^= random >> 31;
+
+        (random >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Build a summary entry for the suppressed Events of a callsite, attributed to its source location.
+/// Summaries are explicit roots, so they never inherit the span of the Event that triggered them.
+fn with_summary(
+    metadata: &'static Metadata<'static>,
+    suppressed: u64,
+    callback: impl FnOnce(&Event<'_>),
+) {
+    let message = format!("{suppressed} entries suppressed by sampling");
+
+    with_internal_event(
+        &SUMMARY_CALLSITE,
+        None,
+        [
+            &message.as_str(),
+            &metadata.level().as_str(),
+            &metadata.file(),
+            &metadata.line(),
+            &metadata.target(),
+            &suppressed,
+        ],
+        callback,
+    );
+}
+
+/// Whether the trace of a span has been sampled, according to either OpenTelemetry or the
+/// nearest [`CloudTraceContext`] in its scope
+pub(crate) fn is_trace_sampled<S>(span: &SpanRef<S>) -> bool
+where
+    S: for<'lookup> LookupSpan<'lookup>,
+{
+    #[cfg(feature = "opentelemetry")]
+    if span.scope().any(|span| is_opentelemetry_sampled(&span)) {
+        return true;
+    }
+
+    span.scope()
+        .find_map(|span| {
+            span.extensions()
+                .get::<CloudTraceContext>()
+                .map(|trace_context| trace_context.sampled)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(feature = "opentelemetry")]
+fn is_opentelemetry_sampled<S>(span: &SpanRef<S>) -> bool
+where
+    S: for<'lookup> LookupSpan<'lookup>,
+{
+    let extensions = span.extensions();
+    let Some(otel_data) = extensions.get::<tracing_opentelemetry::OtelData>() else {
+        return false;
+    };
+
+    let parent_cx = &otel_data.parent_cx;
+
+    (parent_cx.has_active_span() && parent_cx.span().span_context().is_sampled())
+        || otel_data
+            .builder
+            .sampling_result
+            .as_ref()
+            .is_some_and(|result| matches!(result.decision, SamplingDecision::RecordAndSample))
+}
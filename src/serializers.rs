@@ -1,13 +1,9 @@
+use crate::{naming::FieldNames, visitor::SpanFields};
 use serde::ser::{Serialize, SerializeMap, SerializeSeq};
-use serde_json::Value;
-use tracing_core::Subscriber;
-use tracing_subscriber::{
-    fmt::{format::JsonFields, FmtContext, FormattedFields},
-    registry::{LookupSpan, SpanRef},
-};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
 
-/// Serializable tracing span for nesting formatted event fields
-pub(crate) struct SerializableSpan<'a, 'b, S>(&'b SpanRef<'a, S>)
+/// Serializable tracing span for nesting span fields recorded in [`SpanFields`]
+pub(crate) struct SerializableSpan<'a, 'b, S>(&'b SpanRef<'a, S>, &'b FieldNames)
 where
     S: for<'lookup> LookupSpan<'lookup>;
 
@@ -15,8 +11,8 @@ impl<'a, 'b, S> SerializableSpan<'a, 'b, S>
 where
     S: for<'lookup> LookupSpan<'lookup>,
 {
-    pub(crate) fn new(span: &'b SpanRef<'a, S>) -> Self {
-        Self(span)
+    pub(crate) fn new(span: &'b SpanRef<'a, S>, field_names: &'b FieldNames) -> Self {
+        Self(span, field_names)
     }
 }
 
@@ -30,57 +26,47 @@ where
     {
         let name = self.0.name();
         let extensions = self.0.extensions();
+        let fields = extensions.get::<SpanFields>().map(|fields| &fields.0);
 
-        let formatted_fields = extensions
-            .get::<FormattedFields<JsonFields>>()
-            .expect("No fields!");
+        let mut map = serializer.serialize_map(None)?;
+        let mut flattened_name = None;
 
-        let span_length = formatted_fields.fields.len() + 1;
-        let mut map = serializer.serialize_map(Some(span_length))?;
-
-        match serde_json::from_str::<Value>(formatted_fields) {
-            // handle string escaping "properly" (this should be fixed upstream)
-            // https://github.com/tokio-rs/tracing/issues/391
-            Ok(Value::Object(mut fields)) => {
-                let flattened_name = fields.remove("spanName");
-
-                for (key, value) in fields {
-                    map.serialize_entry(&key, &value)?;
-                }
-
-                if let Some(value) = flattened_name {
-                    map.serialize_entry("name", &value)?;
-                } else if !name.is_empty() {
-                    map.serialize_entry("name", &name)?;
-                }
+        for (key, value) in fields.into_iter().flatten() {
+            if key == "spanName" {
+                flattened_name = Some(value);
+            } else {
+                map.serialize_entry(&self.1.convert(key), value)?;
             }
-            Ok(Value::Null) => {}
-            // these two options should be impossible
-            Ok(value) => panic!("Invalid value: {}", value),
-            Err(error) => panic!("Error parsing logs: {}", error),
-        };
+        }
+
+        if let Some(value) = flattened_name {
+            map.serialize_entry("name", value)?;
+        } else if !name.is_empty() {
+            map.serialize_entry("name", name)?;
+        }
 
         map.end()
     }
 }
 
-/// Serializable tracing context for serializing a collection of spans
-pub(crate) struct SerializableContext<'a, 'b, S>(&'b FmtContext<'a, S, JsonFields>)
+/// Serializable tracing context for serializing a collection of spans, from the root span to the
+/// provided leaf span
+pub(crate) struct SerializableContext<'a, 'b, S>(&'b SpanRef<'a, S>, &'b FieldNames)
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>;
+    S: for<'lookup> LookupSpan<'lookup>;
 
 impl<'a, 'b, S> SerializableContext<'a, 'b, S>
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    S: for<'lookup> LookupSpan<'lookup>,
 {
-    pub(crate) fn new(context: &'b FmtContext<'a, S, JsonFields>) -> Self {
-        Self(context)
+    pub(crate) fn new(leaf_span: &'b SpanRef<'a, S>, field_names: &'b FieldNames) -> Self {
+        Self(leaf_span, field_names)
     }
 }
 
 impl<'a, 'b, S> Serialize for SerializableContext<'a, 'b, S>
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    S: for<'lookup> LookupSpan<'lookup>,
 {
     fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
     where
@@ -88,10 +74,8 @@ where
     {
         let mut list = serializer.serialize_seq(None)?;
 
-        if let Some(leaf_span) = self.0.lookup_current() {
-            for span in leaf_span.scope().from_root() {
-                list.serialize_element(&SerializableSpan::new(&span))?;
-            }
+        for span in self.0.scope().from_root() {
+            list.serialize_element(&SerializableSpan::new(&span, self.1))?;
         }
 
         list.end()
@@ -101,6 +85,7 @@ where
 pub(crate) struct SourceLocation<'a> {
     pub(crate) file: &'a str,
     pub(crate) line: Option<u32>,
+    pub(crate) function: Option<&'a str>,
 }
 
 impl<'a> Serialize for SourceLocation<'a> {
@@ -108,13 +93,17 @@ impl<'a> Serialize for SourceLocation<'a> {
     where
         R: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(if self.line.is_some() { 2 } else { 1 }))?;
+        let length = 1 + self.line.iter().count() + self.function.iter().count();
+        let mut map = serializer.serialize_map(Some(length))?;
         map.serialize_entry("file", self.file)?;
         if let Some(line) = self.line {
             // Stackdriver expects the line number to be serialised as a string:
             // https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#LogEntrySourceLocation
             map.serialize_entry("line", &line.to_string())?;
         }
+        if let Some(function) = self.function {
+            map.serialize_entry("function", function)?;
+        }
         map.end()
     }
 }
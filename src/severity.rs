@@ -0,0 +1,133 @@
+use crate::{
+    google::LogSeverity,
+    visitor::{log_metadata, Visitor},
+};
+use std::{fmt, sync::Arc};
+use tracing_core::{Event, Level, Metadata};
+
+type EventFields = serde_json::Map<String, serde_json::Value>;
+
+type MapSeverity = dyn Fn(&Metadata<'_>, &EventFields, LogSeverity) -> LogSeverity + Send + Sync;
+
+/// Policy for deriving the [`LogSeverity`] of Events.
+///
+/// By default, severities are derived from the `Level` of an Event, unless the Event records an
+/// explicit `severity` field. Level-derived severities can be overridden for every target or for
+/// specific targets (and their child modules), and a custom function can adjust the resulting
+/// severity of any Event based on its metadata and fields.
+#[derive(Clone, Default)]
+pub struct SeverityMapper {
+    levels: Vec<(Level, LogSeverity)>,
+    targets: Vec<(String, Level, LogSeverity)>,
+    custom: Option<Arc<MapSeverity>>,
+}
+
+impl SeverityMapper {
+    /// Create a mapper that uses the default severity for every level
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map Events at a level to a severity (e.g. `TRACE` to [`LogSeverity::Default`]) for every target
+    pub fn with_level(mut self, level: Level, severity: LogSeverity) -> Self {
+        self.levels.retain(|(rule_level, _)| *rule_level != level);
+        self.levels.push((level, severity));
+        self
+    }
+
+    /// Map Events at a level to a severity for a target and its child modules (e.g. `INFO` events
+    /// from an `audit` target to [`LogSeverity::Notice`]), taking precedence over
+    /// [`with_level`](Self::with_level). The most specific matching target wins.
+    pub fn with_target(
+        mut self,
+        target: impl Into<String>,
+        level: Level,
+        severity: LogSeverity,
+    ) -> Self {
+        let target = target.into();
+        self.targets
+            .retain(|(rule_target, rule_level, _)| *rule_target != target || *rule_level != level);
+        self.targets.push((target, level, severity));
+        self
+    }
+
+    /// Adjust the severity of every Event with a custom function, which receives the Event's
+    /// metadata, its fields (keyed by their recorded names), and the severity derived from its
+    /// `severity` field or its level
+    pub fn with_custom<F>(mut self, map: F) -> Self
+    where
+        F: Fn(&Metadata<'_>, &EventFields, LogSeverity) -> LogSeverity + Send + Sync + 'static,
+    {
+        self.custom = Some(Arc::new(map));
+        self
+    }
+
+    /// Resolve the severity of an Event from its explicit `severity` field (if any) or its level
+    pub(crate) fn severity(
+        &self,
+        meta: &Metadata<'_>,
+        fields: &EventFields,
+        severity: Option<LogSeverity>,
+    ) -> LogSeverity {
+        let severity = severity.unwrap_or_else(|| self.level_severity(meta));
+
+        match self.custom.as_ref() {
+            Some(map) => map(meta, fields, severity),
+            None => severity,
+        }
+    }
+
+    /// Resolve the severity that an Event is written with, ahead of formatting it
+    pub(crate) fn event_severity(&self, event: &Event<'_>) -> LogSeverity {
+        let normalized_meta = log_metadata(event);
+        let meta = normalized_meta.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut visitor = Visitor::default();
+        event.record(&mut visitor);
+
+        if normalized_meta.is_some() {
+            visitor.strip_log_fields();
+        }
+
+        let severity = visitor.values.remove("severity").map(LogSeverity::from);
+        self.severity(meta, &visitor.values, severity)
+    }
+
+    fn level_severity(&self, meta: &Metadata<'_>) -> LogSeverity {
+        let target = meta.target();
+        let level = meta.level();
+
+        self.targets
+            .iter()
+            .filter(|(rule_target, rule_level, _)| {
+                rule_level == level && matches_target(rule_target, target)
+            })
+            .max_by_key(|(rule_target, _, _)| rule_target.len())
+            .map(|(_, _, severity)| *severity)
+            .or_else(|| {
+                self.levels
+                    .iter()
+                    .find(|(rule_level, _)| rule_level == level)
+                    .map(|(_, severity)| *severity)
+            })
+            .unwrap_or_else(|| LogSeverity::from(level))
+    }
+}
+
+/// Targets match themselves and their child modules
+fn matches_target(rule_target: &str, target: &str) -> bool {
+    target
+        .strip_prefix(rule_target)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+impl fmt::Debug for SeverityMapper {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("SeverityMapper")
+            .field("levels", &self.levels)
+            .field("targets", &self.targets)
+            .field("custom", &self.custom.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
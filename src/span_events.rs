@@ -0,0 +1,65 @@
+use crate::{
+    google::format_duration,
+    internal_event::{internal_callsite, with_internal_event},
+};
+use std::time::{Duration, Instant};
+use tracing_core::{span::Id, Event, Metadata};
+
+internal_callsite!(
+    CLOSE_ENTRY_CALLSITE,
+    "close",
+    &["message", "severity", "time.busy", "time.idle"]
+);
+
+/// Span extension tracking the time a span spends entered (busy) and exited (idle)
+#[derive(Debug)]
+pub(crate) struct SpanTimings {
+    busy: Duration,
+    idle: Duration,
+    last: Instant,
+}
+
+impl SpanTimings {
+    pub(crate) fn new() -> Self {
+        Self {
+            busy: Duration::ZERO,
+            idle: Duration::ZERO,
+            last: Instant::now(),
+        }
+    }
+
+    pub(crate) fn enter(&mut self) {
+        let now = Instant::now();
+        self.idle += now.saturating_duration_since(self.last);
+        self.last = now;
+    }
+
+    pub(crate) fn exit(&mut self) {
+        let now = Instant::now();
+        self.busy += now.saturating_duration_since(self.last);
+        self.last = now;
+    }
+}
+
+/// Build the entry written when a span closes (named by the span, at the span's level) and hand it
+/// to a callback
+pub(crate) fn with_close_entry(
+    span: &Id,
+    metadata: &Metadata<'_>,
+    mut timings: SpanTimings,
+    callback: impl FnOnce(&Event<'_>),
+) {
+    timings.enter();
+
+    with_internal_event(
+        &CLOSE_ENTRY_CALLSITE,
+        Some(span),
+        [
+            &metadata.name(),
+            &metadata.level().as_str(),
+            &format_duration(timings.busy).as_str(),
+            &format_duration(timings.idle).as_str(),
+        ],
+        callback,
+    );
+}
@@ -0,0 +1,156 @@
+use crate::naming::FieldNames;
+use serde_json::{Map, Value};
+
+/// Marker appended to strings that have been shortened to fit the entry size limit
+const TRUNCATION_MARKER: &str = "…";
+
+/// Serialized size (in bytes) below which messages and span fields are never shortened
+const MIN_TRUNCATED_SIZE: usize = 128;
+
+/// Top-level keys of an entry that are never shrunk, since Cloud Logging reads them as LogEntry
+/// fields rather than as part of the `jsonPayload`
+const RESERVED_KEYS: [&str; 3] = ["time", "severity", "target"];
+
+/// Shrink a serialized LogEntry to fit within `max_size` bytes, marking it as truncated.
+///
+/// Entries are shrunk in a predictable order:
+/// 1. a long `message` string is shortened
+/// 2. the largest span fields (in both `span` and `spans`) are shortened, largest first
+/// 3. the `spans` array is removed
+/// 4. the largest remaining `jsonPayload` fields are shortened (or replaced), largest first
+///
+/// Messages and span fields are never shortened below `MIN_TRUNCATED_SIZE` bytes until the last
+/// stage, which replaces fields with a truncation marker if shortening them isn't enough.
+///
+/// Truncated entries include `truncated` and `originalSize` labels (named according to the
+/// formatter's naming policy) so that the data loss is visible in Cloud Logging.
+pub(crate) fn truncate_entry(
+    entry: &[u8],
+    max_size: usize,
+    field_names: &FieldNames,
+) -> serde_json::Result<Vec<u8>> {
+    let mut map: Map<String, Value> = serde_json::from_slice(entry)?;
+
+    let labels = map
+        .entry("logging.googleapis.com/labels")
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    if let Value::Object(labels) = labels {
+        labels.insert(
+            field_names.convert("truncated").into_owned(),
+            Value::from("true"),
+        );
+        labels.insert(
+            field_names.convert("original_size").into_owned(),
+            Value::from(entry.len().to_string()),
+        );
+    }
+
+    let mut excess = measure_excess(&map, max_size)?;
+
+    if excess > 0 {
+        match map.get_mut("message") {
+            Some(Value::String(message)) if message.len() > MIN_TRUNCATED_SIZE => {
+                truncate_string(message, excess);
+                excess = measure_excess(&map, max_size)?;
+            }
+            _ => (),
+        }
+    }
+
+    while excess > 0 {
+        let Some(value) = largest_span_field(&mut map) else {
+            break;
+        };
+
+        match value {
+            Value::String(value) => truncate_string(value, excess),
+            value => *value = Value::from(TRUNCATION_MARKER),
+        }
+
+        excess = measure_excess(&map, max_size)?;
+    }
+
+    if excess > 0 {
+        map.remove("spans");
+        excess = measure_excess(&map, max_size)?;
+    }
+
+    while excess > 0 {
+        let Some(value) = largest_payload_field(&mut map) else {
+            break;
+        };
+
+        match value {
+            Value::String(value) if value.len() > MIN_TRUNCATED_SIZE => {
+                truncate_string(value, excess)
+            }
+            value => *value = Value::from(TRUNCATION_MARKER),
+        }
+
+        excess = measure_excess(&map, max_size)?;
+    }
+
+    serde_json::to_vec(&map)
+}
+
+/// Number of bytes that the serialized entry exceeds the maximum size by
+fn measure_excess(map: &Map<String, Value>, max_size: usize) -> serde_json::Result<usize> {
+    Ok(serde_json::to_vec(map)?.len().saturating_sub(max_size))
+}
+
+/// Shorten a string by up to `excess` bytes (on a character boundary), marking it as truncated
+fn truncate_string(value: &mut String, excess: usize) {
+    let mut length = value
+        .len()
+        .saturating_sub(excess + TRUNCATION_MARKER.len())
+        .max(MIN_TRUNCATED_SIZE - TRUNCATION_MARKER.len());
+
+    while !value.is_char_boundary(length) {
+        length -= 1;
+    }
+
+    value.truncate(length);
+    value.push_str(TRUNCATION_MARKER);
+}
+
+/// Find the largest span field value that is still larger than the truncation minimum
+fn largest_span_field(map: &mut Map<String, Value>) -> Option<&mut Value> {
+    let mut spans: Vec<&mut Map<String, Value>> = vec![];
+
+    for (key, value) in map.iter_mut() {
+        match (key.as_str(), value) {
+            ("span", Value::Object(span)) => spans.push(span),
+            ("spans", Value::Array(list)) => {
+                spans.extend(list.iter_mut().filter_map(Value::as_object_mut))
+            }
+            _ => (),
+        }
+    }
+
+    spans
+        .into_iter()
+        .flat_map(|span| span.iter_mut())
+        .filter(|(key, _)| key.as_str() != "name")
+        .map(|(_, value)| (value.to_string().len(), value))
+        .filter(|(size, value)| match value {
+            Value::String(value) => value.len() > MIN_TRUNCATED_SIZE,
+            _ => *size > MIN_TRUNCATED_SIZE,
+        })
+        .max_by_key(|(size, _)| *size)
+        .map(|(_, value)| value)
+}
+
+/// Find the largest top-level payload field that can still be shrunk
+fn largest_payload_field(map: &mut Map<String, Value>) -> Option<&mut Value> {
+    let marker_size = Value::from(TRUNCATION_MARKER).to_string().len();
+
+    map.iter_mut()
+        .filter(|(key, _)| {
+            !RESERVED_KEYS.contains(&key.as_str()) && !key.starts_with("logging.googleapis.com/")
+        })
+        .map(|(_, value)| (value.to_string().len(), value))
+        .filter(|(size, _)| *size > marker_size)
+        .max_by_key(|(size, _)| *size)
+        .map(|(_, value)| value)
+}
@@ -1,152 +1,140 @@
-use crate::google::LogSeverity;
-use inflector::Inflector;
-use serde::ser::SerializeMap;
-use std::{collections::BTreeMap, fmt};
-use tracing_core::Field;
-use tracing_subscriber::field::{Visit, VisitOutput};
-
-/// Visitor for Stackdriver events that formats custom fields
-pub(crate) struct Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    values: BTreeMap<&'a str, serde_json::Value>,
-    severity: LogSeverity,
-    serializer: S,
+use crate::google::CloudTraceContext;
+use std::fmt;
+use tracing_core::{Event, Field, Metadata};
+use tracing_subscriber::field::Visit;
+
+/// Visitor for Stackdriver events that collects fields as JSON values
+#[derive(Debug, Default)]
+pub(crate) struct Visitor {
+    pub(crate) values: serde_json::Map<String, serde_json::Value>,
+    // the first recorded error and its chain of sources
+    pub(crate) error_chain: Option<String>,
 }
 
-impl<'a, S> Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    /// Returns a new default visitor using the provided writer
-    pub(crate) fn new(severity: LogSeverity, serializer: S) -> Self {
-        Self {
-            values: BTreeMap::new(),
-            severity,
-            serializer,
+impl Visitor {
+    /// Leave out the `log.*` fields through which Events bridged from the `log` crate describe the
+    /// original record (which are written through its normalized metadata instead), except for its
+    /// module path
+    pub(crate) fn strip_log_fields(&mut self) {
+        if let Some(module_path) = self.values.remove("log.module_path") {
+            self.values.insert("module_path".to_string(), module_path);
         }
+
+        self.values.retain(|key, _| !key.starts_with("log."));
     }
 }
 
-impl<'a, S> VisitOutput<fmt::Result> for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    fn finish(mut self) -> fmt::Result {
-        let inner = || {
-            let severity = self
-                .values
-                .remove("severity")
-                .map(LogSeverity::from)
-                .unwrap_or(self.severity);
-
-            self.serializer.serialize_entry("severity", &severity)?;
-
-            let mut http_request = BTreeMap::new();
-            let mut labels = BTreeMap::new();
-
-            for (key, value) in self.values {
-                let mut key_segments = key.splitn(2, '.');
-
-                match (key_segments.next(), key_segments.next()) {
-                    (Some("http_request"), Some(request_key)) => {
-                        http_request.insert(request_key.to_camel_case(), value);
-                    }
-                    (Some("labels"), Some(label_key)) => {
-                        let value = match value {
-                            serde_json::Value::String(value) => value,
-                            _ => value.to_string(),
-                        };
-
-                        labels.insert(label_key.to_camel_case(), value);
-                    }
-                    (Some("insert_id"), None) => {
-                        let value = match value {
-                            serde_json::Value::String(value) => value,
-                            _ => value.to_string(),
-                        };
-
-                        self.serializer
-                            .serialize_entry("logging.googleapis.com/insertId", &value)?;
-                    }
-                    (Some(key), None) => self
-                        .serializer
-                        .serialize_entry(&key.to_camel_case(), &value)?,
-                    _ => self
-                        .serializer
-                        .serialize_entry(&key.to_camel_case(), &value)?,
-                }
-            }
+/// Metadata of the original record of an Event bridged from the `log` crate, which describes its
+/// target and location through `log.*` fields rather than through the Event's own metadata
+#[cfg(feature = "log")]
+pub(crate) fn log_metadata<'a>(event: &'a Event<'a>) -> Option<Metadata<'a>> {
+    use tracing_log::NormalizeEvent;
 
-            if !http_request.is_empty() {
-                self.serializer
-                    .serialize_entry("httpRequest", &http_request)?;
-            }
-
-            if !labels.is_empty() {
-                self.serializer
-                    .serialize_entry("logging.googleapis.com/labels", &labels)?;
-            }
+    event.normalized_metadata()
+}
 
-            self.serializer.end()
-        };
+/// Events are only normalized when the `log` bridge is enabled
+#[cfg(not(feature = "log"))]
+pub(crate) fn log_metadata<'a>(_event: &'a Event<'a>) -> Option<Metadata<'a>> {
+    None
+}
 
-        if inner().is_err() {
-            Err(fmt::Error)
-        } else {
-            Ok(())
-        }
+impl Visit for Visitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.values.insert(
+            field.name().to_string(),
+            serde_json::Value::Number(serde_json::Number::from_f64(value).unwrap_or_else(|| {
+                // tracing::debug!(target: "tracing_stackdriver::event_formatter", "f64 is not finite, using 0.0 instead");
+                serde_json::Number::from(0)
+            })),
+        );
     }
-}
 
-impl<'a, S> Visit for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
     fn record_i64(&mut self, field: &Field, value: i64) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.values.insert(
+            field.name().to_string(),
+            serde_json::Value::Number(value.into()),
+        );
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.values.insert(
+            field.name().to_string(),
+            serde_json::Value::Number(value.into()),
+        );
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
         self.values
-            .insert(field.name(), serde_json::Value::from(value));
+            .insert(field.name().to_string(), serde_json::Value::Bool(value));
     }
 
     fn record_str(&mut self, field: &Field, value: &str) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.values.insert(
+            field.name().to_string(),
+            serde_json::Value::String(value.to_string()),
+        );
     }
 
-    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        if self.error_chain.is_none() {
+            let mut error_chain = value.to_string();
+            let mut source = value.source();
+
+            while let Some(error) = source {
+                error_chain.push_str("\nCaused by: ");
+                error_chain.push_str(&error.to_string());
+                source = error.source();
+            }
+
+            self.error_chain = Some(error_chain);
+        }
+
         self.values.insert(
-            field.name(),
-            serde_json::Value::from(format!("{:?}", value)),
+            field.name().to_string(),
+            serde_json::Value::String(value.to_string()),
         );
     }
 
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            // Skip fields that are actually log metadata that have already been handled
+            name if name.starts_with("log.") => (),
+            name if name.starts_with("event.") => (),
+            "message" => {
+                self.values.insert(
+                    "message".to_string(), // Use "message" as the key for the message field
+                    serde_json::Value::String(format!("{:?}", value)),
+                );
+            }
+            _ => {
+                self.values.insert(
+                    field.name().to_string(),
+                    serde_json::Value::String(format!("{:?}", value)),
+                );
+            }
+        }
+    }
+
     #[cfg(all(tracing_unstable, feature = "valuable"))]
     fn record_value(&mut self, field: &Field, value: valuable::Value<'_>) {
-        let value = serde_json::to_value(valuable_serde::Serializable::new(value)).unwrap();
-
-        self.values.insert(field.name(), value);
+        match serde_json::to_value(valuable_serde::Serializable::new(value)) {
+            Ok(value) => self.values.insert(field.name().to_string(), value),
+            Err(_) => self.values.insert(
+                field.name().to_string(),
+                serde_json::Value::String(format!("{:?}", value)),
+            ),
+        };
     }
 }
 
-impl<'a, S> fmt::Debug for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter
-            .debug_struct("Visitor")
-            .field("values", &self.values)
-            .finish()
+/// Span extension holding the fields recorded on a span as JSON values, ready for serialization
+#[derive(Debug, Default)]
+pub(crate) struct SpanFields(pub(crate) serde_json::Map<String, serde_json::Value>);
+
+impl SpanFields {
+    /// The [`CloudTraceContext`] of a `trace_context` field, if one was recorded
+    pub(crate) fn trace_context(&self) -> Option<CloudTraceContext> {
+        self.0.get("trace_context")?.as_str()?.parse().ok()
     }
 }
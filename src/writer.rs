@@ -1,7 +1,15 @@
 use std::{
+    collections::VecDeque,
     fmt::{Formatter, Write},
     io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
+use tracing_subscriber::fmt::MakeWriter;
 
 /// Utility newtype for converting between fmt::Write and io::Write
 // https://docs.rs/tracing-subscriber/latest/src/tracing_subscriber/fmt/writer.rs.html
@@ -20,11 +28,9 @@ impl<'a> io::Write for WriteAdaptor<'a> {
         let s =
             std::str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-        self.fmt_write
-            .write_str(s)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        self.fmt_write.write_str(s).map_err(io::Error::other)?;
 
-        Ok(s.as_bytes().len())
+        Ok(s.len())
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -37,3 +43,287 @@ impl<'a> std::fmt::Debug for WriteAdaptor<'a> {
         formatter.pad("WriteAdaptor { .. }")
     }
 }
+
+const DEFAULT_QUEUE_CAPACITY: usize = 128_000;
+const DEFAULT_BATCH_SIZE: usize = 64;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Strategy for handling new entries when the queue of a [`NonBlocking`] writer is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the entry being written, keeping everything already queued
+    #[default]
+    DropNewest,
+    /// Discard the oldest queued entry to make room for the entry being written
+    DropOldest,
+    /// Block the writing thread until the background worker makes room in the queue
+    Block,
+}
+
+/// A MakeWriter that hands serialized entries to a background worker thread through a bounded
+/// queue, so that emitting events never blocks on the underlying `io::Write`.
+///
+/// The worker writes entries in batches, flushing the underlying writer after each batch. Any
+/// entries still queued are written when the accompanying [`WorkerGuard`] is dropped.
+#[derive(Clone, Debug)]
+pub struct NonBlocking {
+    queue: Arc<Queue>,
+}
+
+impl NonBlocking {
+    /// Spawn a background worker for the provided writer using the default configuration
+    pub fn new<W>(writer: W) -> (Self, WorkerGuard)
+    where
+        W: io::Write + Send + 'static,
+    {
+        NonBlockingBuilder::default().finish(writer)
+    }
+
+    /// Create a builder for configuring the queue and batching behavior of the worker
+    pub fn builder() -> NonBlockingBuilder {
+        NonBlockingBuilder::default()
+    }
+
+    /// The number of entries discarded so far because the queue was full
+    pub fn dropped_entries(&self) -> usize {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl io::Write for NonBlocking {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.queue.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for NonBlocking {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Builder for [`NonBlocking`] writers and their background worker
+#[derive(Debug)]
+pub struct NonBlockingBuilder {
+    queue_capacity: usize,
+    batch_size: usize,
+    flush_interval: Duration,
+    overflow_policy: OverflowPolicy,
+}
+
+impl NonBlockingBuilder {
+    /// Sets the maximum number of entries waiting to be written before the overflow policy applies
+    pub fn with_queue_capacity(self, queue_capacity: usize) -> Self {
+        Self {
+            queue_capacity: queue_capacity.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the number of queued entries that triggers an immediate write by the worker
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the longest time an entry waits in the queue before the worker writes a partial batch
+    pub fn with_flush_interval(self, flush_interval: Duration) -> Self {
+        Self {
+            flush_interval,
+            ..self
+        }
+    }
+
+    /// Sets how new entries are handled when the queue is full
+    pub fn with_overflow_policy(self, overflow_policy: OverflowPolicy) -> Self {
+        Self {
+            overflow_policy,
+            ..self
+        }
+    }
+
+    /// Spawn the background worker for the provided writer
+    pub fn finish<W>(self, writer: W) -> (NonBlocking, WorkerGuard)
+    where
+        W: io::Write + Send + 'static,
+    {
+        let queue = Arc::new(Queue {
+            state: Mutex::default(),
+            available: Condvar::new(),
+            space: Condvar::new(),
+            capacity: self.queue_capacity,
+            batch_size: self.batch_size,
+            overflow_policy: self.overflow_policy,
+            dropped: AtomicUsize::new(0),
+        });
+
+        let worker = {
+            let queue = queue.clone();
+            let flush_interval = self.flush_interval;
+
+            thread::Builder::new()
+                .name("tracing-stackdriver-worker".to_string())
+                .spawn(move || queue.work(writer, flush_interval))
+                .expect("Unable to spawn tracing-stackdriver worker thread")
+        };
+
+        (
+            NonBlocking {
+                queue: queue.clone(),
+            },
+            WorkerGuard {
+                queue,
+                worker: Some(worker),
+            },
+        )
+    }
+}
+
+impl Default for NonBlockingBuilder {
+    fn default() -> Self {
+        Self {
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// Guard that shuts down the background worker of a [`NonBlocking`] writer when dropped,
+/// blocking until every queued entry has been written and flushed.
+#[must_use = "dropping the guard immediately shuts down the background worker"]
+#[derive(Debug)]
+pub struct WorkerGuard {
+    queue: Arc<Queue>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        self.queue.lock().shutdown = true;
+        self.queue.available.notify_all();
+        self.queue.space.notify_all();
+
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                eprintln!("[tracing-stackdriver] Background worker panicked before shutting down");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    entries: VecDeque<Vec<u8>>,
+    shutdown: bool,
+}
+
+/// Bounded queue shared between NonBlocking writers and their worker
+#[derive(Debug)]
+struct Queue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+    space: Condvar,
+    capacity: usize,
+    batch_size: usize,
+    overflow_policy: OverflowPolicy,
+    dropped: AtomicUsize,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn push(&self, entry: Vec<u8>) {
+        let mut state = self.lock();
+
+        while !state.shutdown && state.entries.len() >= self.capacity {
+            match self.overflow_policy {
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                OverflowPolicy::DropOldest => {
+                    state.entries.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::Block => {
+                    state = self
+                        .space
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+
+        if state.shutdown {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        state.entries.push_back(entry);
+
+        if state.entries.len() >= self.batch_size {
+            self.available.notify_one();
+        }
+    }
+
+    /// Write batches of entries until shutdown, draining the queue before returning
+    fn work<W>(&self, mut writer: W, flush_interval: Duration)
+    where
+        W: io::Write,
+    {
+        loop {
+            let (batch, shutdown) = {
+                let mut state = self.lock();
+                let deadline = Instant::now() + flush_interval;
+
+                while !state.shutdown && state.entries.len() < self.batch_size {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+
+                    if timeout.is_zero() {
+                        break;
+                    }
+
+                    state = self
+                        .available
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0;
+                }
+
+                let length = state.entries.len().min(self.batch_size);
+                let batch = state.entries.drain(..length).collect::<Vec<_>>();
+                self.space.notify_all();
+
+                (batch, state.shutdown && state.entries.is_empty())
+            };
+
+            if !batch.is_empty() {
+                let result = batch
+                    .iter()
+                    .try_for_each(|entry| writer.write_all(entry))
+                    .and_then(|_| writer.flush());
+
+                if let Err(error) = result {
+                    eprintln!("[tracing-stackdriver] Unable to write log entries: {error}");
+                }
+            }
+
+            if shutdown {
+                break;
+            }
+        }
+    }
+}
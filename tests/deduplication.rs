@@ -0,0 +1,178 @@
+use helpers::run_with_tracing_layer;
+use std::time::Duration;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing_stackdriver::{entry::StackdriverEntry, LogSeverity};
+
+mod helpers;
+
+fn timestamp(event: &StackdriverEntry, key: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(event.fields[key].as_str().unwrap(), &Rfc3339).unwrap()
+}
+
+fn repeat_counts(events: &[StackdriverEntry]) -> Vec<(&str, Option<u64>)> {
+    events
+        .iter()
+        .map(|event| {
+            (
+                event.message.as_deref().unwrap(),
+                event
+                    .fields
+                    .get("repeatCount")
+                    .map(|count| count.as_u64().unwrap()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn collapses_repeated_events() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_secs(60));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for _ in 0..4 {
+            tracing::warn!(queue = "jobs", "queue is backed up");
+        }
+
+        tracing::info!("caught up");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].message.as_deref(), Some("queue is backed up"));
+    assert!(!events[0].fields.contains_key("repeatCount"));
+
+    let repeat = &events[1];
+    assert_eq!(repeat.message.as_deref(), Some("queue is backed up"));
+    assert_eq!(repeat.fields["queue"], "jobs");
+    assert_eq!(repeat.severity, LogSeverity::Warning);
+    assert_eq!(repeat.fields["repeatCount"], 3);
+    assert!(timestamp(repeat, "firstSeen") <= timestamp(repeat, "lastSeen"));
+    assert_eq!(
+        repeat
+            .source_location
+            .as_ref()
+            .map(|location| location.file.as_str()),
+        Some("tests/deduplication.rs")
+    );
+
+    assert_eq!(events[2].message.as_deref(), Some("caught up"));
+}
+
+#[test]
+fn keeps_events_with_different_fields() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_secs(60));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for attempt in 0..3 {
+            tracing::info!(attempt, "retrying");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(repeat_counts(&events), [("retrying", None); 3]);
+}
+
+#[test]
+fn keeps_identical_events_from_different_callsites() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_secs(60));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!("hello!");
+        tracing::info!("hello!");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+}
+
+#[test]
+fn keeps_repeats_within_closed_spans() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_secs(60));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info_span!("outer").in_scope(|| {
+            for _ in 0..3 {
+                tracing::warn!("retrying");
+            }
+        });
+
+        tracing::info!("done");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        repeat_counts(&events),
+        [("retrying", None), ("retrying", Some(2)), ("done", None)]
+    );
+}
+
+#[test]
+fn attributes_repeats_to_the_span_of_the_repeated_event() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_secs(60));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info_span!("request", labels.tenant = "a").in_scope(|| {
+            for _ in 0..2 {
+                tracing::warn!("retrying");
+            }
+        });
+
+        for _ in 0..2 {
+            tracing::warn!("retrying");
+        }
+
+        tracing::info_span!("request", labels.tenant = "b").in_scope(|| tracing::info!("done"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 5);
+
+    let within_span = &events[1];
+    assert_eq!(within_span.fields["repeatCount"], 1);
+    assert_eq!(within_span.target, "deduplication");
+    assert_eq!(
+        within_span
+            .span
+            .as_ref()
+            .and_then(|span| span.name.as_deref()),
+        Some("request")
+    );
+    assert_eq!(within_span.labels["tenant"], "a");
+
+    let outside_spans = &events[3];
+    assert_eq!(outside_spans.fields["repeatCount"], 1);
+    assert_eq!(outside_spans.target, "deduplication");
+    assert!(outside_spans.span.is_none());
+    assert!(outside_spans.spans.is_empty());
+    assert!(outside_spans.labels.is_empty());
+
+    assert_eq!(events[4].labels["tenant"], "b");
+}
+
+#[test]
+fn flushes_repeats_after_the_timeout() {
+    let layer = tracing_stackdriver::layer().with_deduplication(Duration::from_millis(20));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for _ in 0..2 {
+            tracing::info!("polling");
+        }
+
+        std::thread::sleep(Duration::from_millis(40));
+
+        for _ in 0..2 {
+            tracing::info!("polling");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        repeat_counts(&events),
+        [
+            ("polling", None),
+            ("polling", Some(1)),
+            ("polling", None),
+            ("polling", Some(1)),
+        ]
+    );
+}
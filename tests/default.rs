@@ -2,17 +2,15 @@
 use std::collections::BTreeMap;
 
 use helpers::run_with_tracing;
-use mocks::{MockDefaultEvent, MockEventWithSpan};
 use serde::Deserialize;
 use time::OffsetDateTime;
-use tracing_stackdriver::LogSeverity;
+use tracing_stackdriver::{entry::StackdriverEntry, LogSeverity};
 
 mod helpers;
-mod mocks;
 
 #[test]
 fn includes_span() {
-    let events = run_with_tracing::<MockEventWithSpan>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         let span = tracing::info_span!("stackdriver_span", foo = "bar");
         let _guard = span.enter();
         tracing::info!("some stackdriver message");
@@ -20,9 +18,24 @@ fn includes_span() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    let span = event.span();
-    assert_eq!(span.span_name, "stackdriver_span");
-    assert_eq!(span.foo, "bar");
+    let span = event.parent_span().expect("No span heard");
+    assert_eq!(span.name.as_deref(), Some("stackdriver_span"));
+    assert_eq!(span.fields["foo"], "bar");
+}
+
+#[test]
+fn includes_span_fields_recorded_later() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let span = tracing::info_span!("stackdriver_span", foo = tracing::field::Empty);
+        let _guard = span.enter();
+        span.record("foo", "recorded");
+        tracing::info!("some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.parent_span().unwrap().fields["foo"], "recorded");
+    assert_eq!(event.spans[0].fields["foo"], "recorded");
 }
 
 #[test]
@@ -35,26 +48,27 @@ fn parses_payload_with_only_spans_array() {
         ]
     });
 
-    let event: MockEventWithSpan = serde_json::from_value(payload).expect("Failed to parse payload");
-    let span = event.span();
-    assert_eq!(span.span_name, "child");
-    assert_eq!(span.foo, "baz");
-    assert_eq!(event.spans().len(), 2);
+    let event: StackdriverEntry = serde_json::from_value(payload).expect("Failed to parse payload");
+    let span = event.parent_span().expect("No span parsed");
+    assert_eq!(span.name.as_deref(), Some("child"));
+    assert_eq!(span.fields["foo"], "baz");
+    assert_eq!(event.spans.len(), 2);
+    assert_eq!(event.spans[0].name.as_deref(), Some("root"));
 }
 
 #[test]
 fn includes_correct_custom_fields() {
     let start = OffsetDateTime::now_utc();
 
-    let events = run_with_tracing::<MockDefaultEvent>(
+    let events = run_with_tracing::<StackdriverEntry>(
         || tracing::info!(target: "test target", "some stackdriver message"),
     )
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert!(event.time > start);
+    assert!(event.time.expect("No time heard") > start);
     assert_eq!(event.target, "test target");
-    assert_eq!(event.severity, "INFO");
+    assert_eq!(event.severity, LogSeverity::Info);
 }
 
 #[test]
@@ -75,18 +89,18 @@ fn includes_custom_fields_with_dot() {
 
 #[test]
 fn handles_stringly_severity_override() {
-    let events = run_with_tracing::<MockDefaultEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(severity = "notice", "notice me, senpai!")
     })
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert_eq!(event.severity, "NOTICE");
+    assert_eq!(event.severity, LogSeverity::Notice);
 }
 
 #[test]
 fn handles_enum_severity_override() {
-    let events = run_with_tracing::<MockDefaultEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             severity = %LogSeverity::Notice,
             "notice me, senpai!"
@@ -95,12 +109,12 @@ fn handles_enum_severity_override() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert_eq!(event.severity, "NOTICE");
+    assert_eq!(event.severity, LogSeverity::Notice);
 }
 
 #[test]
 fn includes_correct_timestamps() {
-    let mut events = run_with_tracing::<MockDefaultEvent>(|| {
+    let mut events = run_with_tracing::<StackdriverEntry>(|| {
         let span = tracing::info_span!("test span", foo = "bar");
         let _guard = span.enter();
         tracing::info!(target: "first target", "some stackdriver message");
@@ -111,6 +125,7 @@ fn includes_correct_timestamps() {
 
     let first_event = events.next().expect("Error logging first event");
     let second_event = events.next().expect("Error logging second event");
+    assert!(first_event.time.is_some());
     assert!(first_event.time < second_event.time);
 }
 
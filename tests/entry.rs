@@ -0,0 +1,105 @@
+use helpers::run_with_tracing_layer;
+use tracing_stackdriver::{
+    entry::{HttpRequest, Operation, SourceLocation, StackdriverEntry, TraceFields},
+    CloudTraceConfiguration, LogSeverity,
+};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+#[test]
+fn reads_back_every_special_field() {
+    let layer = tracing_stackdriver::layer()
+        .with_cloud_trace(CloudTraceConfiguration {
+            project_id: PROJECT_ID.to_owned(),
+        })
+        .with_default_labels([("service", "checkout")])
+        .with_static_fields([("version", "1.2.3")]);
+
+    let mut line = 0;
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info_span!(
+            "request",
+            trace_context = format!("{TRACE_ID}/1;o=1"),
+            http_request.request_method = "GET",
+            operation.id = "checkout-42",
+        )
+        .in_scope(|| {
+            line = line!() + 1;
+            tracing::warn!(
+                http_request.status = 200,
+                insert_id = "entry-1",
+                user_id = 7,
+                "checked out"
+            );
+        });
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.severity, LogSeverity::Warning);
+    assert_eq!(event.target, "entry");
+    assert_eq!(event.message.as_deref(), Some("checked out"));
+    assert_eq!(event.insert_id.as_deref(), Some("entry-1"));
+    assert_eq!(event.labels["service"], "checkout");
+    assert_eq!(event.fields["userId"], 7);
+    assert_eq!(event.fields["version"], "1.2.3");
+    assert_eq!(
+        event.http_request,
+        Some(HttpRequest {
+            request_method: Some("GET".to_string()),
+            status: Some(200),
+            ..HttpRequest::default()
+        })
+    );
+    assert_eq!(
+        event.operation,
+        Some(Operation {
+            id: Some("checkout-42".to_string()),
+            first: Some(true),
+            ..Operation::default()
+        })
+    );
+    assert_eq!(
+        event.source_location,
+        Some(SourceLocation {
+            file: "tests/entry.rs".to_string(),
+            line: Some(line.to_string()),
+            function: None,
+        })
+    );
+    assert_eq!(
+        event.trace,
+        TraceFields {
+            trace: Some(format!("projects/{PROJECT_ID}/traces/{TRACE_ID}")),
+            span_id: Some("0000000000000001".to_string()),
+            trace_sampled: true,
+        }
+    );
+
+    let span = event.parent_span().expect("No span heard");
+    assert_eq!(span.name.as_deref(), Some("request"));
+    assert_eq!(event.spans.len(), 1);
+}
+
+#[test]
+fn reads_back_entries_without_special_fields() {
+    let layer = tracing_stackdriver::layer().with_source_location(false);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || tracing::debug!(done = true))
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.severity, LogSeverity::Debug);
+    assert!(event.message.is_none());
+    assert!(event.http_request.is_none());
+    assert!(event.labels.is_empty());
+    assert!(event.source_location.is_none());
+    assert!(event.parent_span().is_none());
+    assert_eq!(event.trace, TraceFields::default());
+    assert_eq!(event.fields["done"], true);
+}
@@ -0,0 +1,207 @@
+use std::{path::PathBuf, sync::Mutex};
+use tracing_stackdriver::{EnvConfigError, Layer};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+static CONFIGURATION_VARIABLES: [&str; 5] = [
+    "STACKDRIVER_SOURCE_LOCATION",
+    "STACKDRIVER_PROJECT_ID",
+    "STACKDRIVER_FIELD_CASE",
+    "STACKDRIVER_OUTPUT",
+    "STACKDRIVER_LABELS",
+];
+
+type Event = serde_json::Map<String, serde_json::Value>;
+
+/// Environment variables are process-wide, so tests that depend on them take turns
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+fn with_environment<T>(variables: &[(&str, &str)], callback: impl FnOnce() -> T) -> T {
+    let _lock = ENVIRONMENT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+
+    for key in CONFIGURATION_VARIABLES {
+        std::env::remove_var(key);
+    }
+
+    for (key, value) in variables {
+        std::env::set_var(key, value);
+    }
+
+    callback()
+}
+
+fn output_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "tracing-stackdriver-{}-{name}.log",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Run a traced callback against a Layer configured from the environment, writing to a file
+fn run_with_env(variables: &[(&str, &str)], callback: impl FnOnce()) -> Vec<Event> {
+    let path = output_path(&format!("{:?}", std::thread::current().id()));
+    let output = format!("file:{}", path.display());
+    let mut variables = variables.to_vec();
+    variables.push(("STACKDRIVER_OUTPUT", &output));
+
+    let layer = with_environment(&variables, Layer::<Registry, _>::from_env)
+        .expect("Error configuring layer from the environment");
+
+    tracing::subscriber::with_default(Registry::default().with(layer), callback);
+
+    let output = std::fs::read(&path).expect("Error reading output file");
+    let _ = std::fs::remove_file(&path);
+
+    serde_json::Deserializer::from_slice(&output)
+        .into_iter()
+        .collect::<serde_json::Result<_>>()
+        .expect("Error converting output file to JSON")
+}
+
+fn from_env_error(variables: &[(&str, &str)]) -> EnvConfigError {
+    match with_environment(variables, Layer::<Registry, _>::from_env) {
+        Ok(_) => panic!("Expected an error configuring layer from {variables:?}"),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn uses_defaults_without_configuration() {
+    let events = run_with_env(&[], || tracing::info!(user_id = 42, "hello!"));
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["message"], "hello!");
+    assert_eq!(events[0]["userId"], 42);
+    assert!(events[0].contains_key("logging.googleapis.com/sourceLocation"));
+    assert!(!events[0].contains_key("logging.googleapis.com/labels"));
+}
+
+#[test]
+fn configures_layer_from_variables() {
+    let events = run_with_env(
+        &[
+            ("STACKDRIVER_SOURCE_LOCATION", "false"),
+            ("STACKDRIVER_PROJECT_ID", "my_project_123"),
+            ("STACKDRIVER_FIELD_CASE", "snake"),
+            ("STACKDRIVER_LABELS", "env=prod, team=payments"),
+        ],
+        || {
+            tracing::info_span!(
+                "request",
+                trace_context = "105445aa7843bc8bf206b12000100000/1;o=1"
+            )
+            .in_scope(|| tracing::info!(userId = 42, labels.team = "billing", "hello!"));
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+
+    let event = &events[0];
+    assert_eq!(event["user_id"], 42);
+    assert!(!event.contains_key("logging.googleapis.com/sourceLocation"));
+    assert_eq!(
+        event["logging.googleapis.com/trace"],
+        "projects/my_project_123/traces/105445aa7843bc8bf206b12000100000"
+    );
+    assert_eq!(
+        event["logging.googleapis.com/labels"],
+        serde_json::json!({ "env": "prod", "team": "billing" })
+    );
+}
+
+#[test]
+fn appends_to_output_files() {
+    let path = output_path("append");
+    std::fs::write(&path, "").expect("Error creating output file");
+    let output = format!("file:{}", path.display());
+
+    for message in ["first", "second"] {
+        let layer = with_environment(&[("STACKDRIVER_OUTPUT", &output)], || {
+            Layer::<Registry, _>::from_env()
+        })
+        .expect("Error configuring layer from the environment");
+
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            tracing::info!("{message}")
+        });
+    }
+
+    let output = std::fs::read(&path).expect("Error reading output file");
+    let _ = std::fs::remove_file(&path);
+    let events = serde_json::Deserializer::from_slice(&output)
+        .into_iter::<Event>()
+        .collect::<serde_json::Result<Vec<_>>>()
+        .expect("Error converting output file to JSON");
+
+    let messages = events
+        .iter()
+        .map(|event| event["message"].as_str().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(messages, ["first", "second"]);
+}
+
+#[test]
+fn ignores_empty_variables() {
+    let result = with_environment(
+        &[
+            ("STACKDRIVER_SOURCE_LOCATION", ""),
+            ("STACKDRIVER_OUTPUT", " "),
+        ],
+        Layer::<Registry, _>::from_env,
+    );
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn rejects_invalid_values() {
+    for (variable, value) in [
+        ("STACKDRIVER_SOURCE_LOCATION", "sometimes"),
+        ("STACKDRIVER_FIELD_CASE", "kebab"),
+        ("STACKDRIVER_OUTPUT", "syslog"),
+        ("STACKDRIVER_OUTPUT", "file:"),
+        ("STACKDRIVER_LABELS", "env=prod,team"),
+        ("STACKDRIVER_LABELS", "=prod"),
+    ] {
+        match from_env_error(&[(variable, value)]) {
+            EnvConfigError::InvalidValue {
+                variable: invalid_variable,
+                value: invalid_value,
+                ..
+            } => {
+                assert_eq!(invalid_variable, variable);
+                assert_eq!(invalid_value, value);
+            }
+            error => panic!("Unexpected error for {variable}={value}: {error}"),
+        }
+    }
+}
+
+#[test]
+fn describes_invalid_values() {
+    let error = from_env_error(&[("STACKDRIVER_FIELD_CASE", "kebab")]);
+
+    assert_eq!(
+        error.to_string(),
+        "STACKDRIVER_FIELD_CASE must be one of `camel`, `snake` or `preserve`, but was set to \"kebab\""
+    );
+}
+
+#[test]
+fn reports_unwritable_output_files() {
+    let path = std::env::temp_dir()
+        .join("tracing-stackdriver-missing-directory")
+        .join("output.log");
+    let output = format!("file:{}", path.display());
+
+    match from_env_error(&[("STACKDRIVER_OUTPUT", &output)]) {
+        EnvConfigError::Output {
+            path: error_path, ..
+        } => assert_eq!(error_path, path),
+        error => panic!("Unexpected error: {error}"),
+    }
+}
@@ -0,0 +1,107 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use std::{error::Error, fmt};
+use tracing_stackdriver::{entry::StackdriverEntry, ServiceContext};
+
+mod helpers;
+
+static REPORTED_ERROR_EVENT_TYPE: &str =
+    "type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent";
+
+#[derive(Debug)]
+struct MockError {
+    message: &'static str,
+    source: Option<Box<MockError>>,
+}
+
+impl fmt::Display for MockError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.message)
+    }
+}
+
+impl Error for MockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|source| source as _)
+    }
+}
+
+fn mock_error() -> MockError {
+    MockError {
+        message: "request failed",
+        source: Some(Box::new(MockError {
+            message: "connection reset",
+            source: Some(Box::new(MockError {
+                message: "broken pipe",
+                source: None,
+            })),
+        })),
+    }
+}
+
+fn run_with_error_reporting(callback: impl FnOnce()) -> Vec<StackdriverEntry> {
+    let layer = tracing_stackdriver::layer()
+        .with_error_reporting(ServiceContext::new("my-service").with_version("1.2.3"));
+
+    run_with_tracing_layer(layer, callback).expect("Error converting test buffer to JSON")
+}
+
+#[test]
+fn reports_errors_with_source_chains() {
+    let error = mock_error();
+
+    let events = run_with_error_reporting(|| {
+        tracing::error!(error = &error as &dyn Error, "something went wrong")
+    });
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.entry_type.as_deref(), Some(REPORTED_ERROR_EVENT_TYPE));
+    assert_eq!(
+        event.service_context,
+        Some(ServiceContext::new("my-service").with_version("1.2.3"))
+    );
+    assert_eq!(
+        event.stack_trace.as_deref(),
+        Some("request failed\nCaused by: connection reset\nCaused by: broken pipe")
+    );
+    assert_eq!(event.message.as_deref(), Some("something went wrong"));
+    assert_eq!(event.fields["error"], "request failed");
+}
+
+#[test]
+fn reports_errors_with_severity_overrides() {
+    let error = mock_error();
+
+    let events = run_with_error_reporting(|| {
+        tracing::warn!(severity = "critical", error = &error as &dyn Error, "oh no")
+    });
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.entry_type.as_deref(), Some(REPORTED_ERROR_EVENT_TYPE));
+}
+
+#[test]
+fn skips_events_below_error_severity() {
+    let error = mock_error();
+
+    let events =
+        run_with_error_reporting(|| tracing::warn!(error = &error as &dyn Error, "retrying"));
+
+    let event = events.first().expect("No event heard");
+    assert!(event.entry_type.is_none());
+    assert!(event.service_context.is_none());
+    assert!(event.stack_trace.is_none());
+}
+
+#[test]
+fn skips_errors_by_default() {
+    let error = mock_error();
+
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        tracing::error!(error = &error as &dyn Error, "something went wrong")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.entry_type.is_none());
+    assert_eq!(event.fields["error"], "request failed");
+}
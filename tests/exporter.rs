@@ -0,0 +1,232 @@
+#![cfg(feature = "exporter")]
+use serde_json::Value;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing_stackdriver::{CloudLoggingExporter, MonitoredResource};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Start a mock entries:write server that accepts a number of requests,
+/// returning the endpoint URL and a channel of (authorization header, JSON body) pairs
+fn mock_server(requests: usize) -> (String, mpsc::Receiver<(Option<String>, Value)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    let endpoint = format!(
+        "http://{}/v2/entries:write",
+        listener
+            .local_addr()
+            .expect("Couldn't read mock server address")
+    );
+
+    (endpoint, serve(listener, requests))
+}
+
+/// Accept a number of requests on a bound mock server,
+/// returning a channel of (authorization header, JSON body) pairs
+fn serve(listener: TcpListener, requests: usize) -> mpsc::Receiver<(Option<String>, Value)> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.expect("Couldn't accept connection");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            let mut authorization = None;
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if let Some((name, value)) = line.split_once(": ") {
+                    match name.to_lowercase().as_str() {
+                        "content-length" => content_length = value.parse().unwrap(),
+                        "authorization" => authorization = Some(value.to_string()),
+                        _ => (),
+                    }
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+                .unwrap();
+
+            sender
+                .send((authorization, serde_json::from_slice(&body).unwrap()))
+                .unwrap();
+        }
+    });
+
+    receiver
+}
+
+#[test]
+fn exports_batches_of_entries() {
+    let (endpoint, requests) = mock_server(2);
+    let exporter = CloudLoggingExporter::new("my-project", "my/log")
+        .with_endpoint(endpoint)
+        .with_batch_size(2)
+        .with_token_provider(|| Some("some-token".to_string()));
+
+    let subscriber = Registry::default().with(tracing_stackdriver::layer().with_writer(exporter));
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(labels.foo = "bar", "first");
+        tracing::warn!(insert_id = 123, "second");
+        tracing::error!(baz = true, "third");
+    });
+
+    let (authorization, batch) = requests.recv().expect("No batch received");
+    assert_eq!(authorization.as_deref(), Some("Bearer some-token"));
+    assert_eq!(batch["logName"], "projects/my-project/logs/my%2Flog");
+    assert_eq!(batch["resource"]["type"], "global");
+    assert_eq!(batch["resource"]["labels"]["project_id"], "my-project");
+
+    let entries = batch["entries"].as_array().expect("Missing entries");
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["severity"], "INFO");
+    assert!(entries[0]["timestamp"].is_string());
+    assert_eq!(entries[0]["labels"]["foo"], "bar");
+    assert_eq!(entries[0]["jsonPayload"]["message"], "first");
+    assert!(entries[0]["sourceLocation"]["file"].is_string());
+    assert!(entries[0]["jsonPayload"].get("severity").is_none());
+    assert!(entries[0]["jsonPayload"].get("time").is_none());
+    assert_eq!(entries[1]["insertId"], "123");
+
+    // remaining entries are sent once the exporter is dropped
+    let (_, batch) = requests.recv().expect("No final batch received");
+    let entries = batch["entries"].as_array().expect("Missing entries");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["severity"], "ERROR");
+    assert_eq!(entries[0]["jsonPayload"]["baz"], true);
+}
+
+#[test]
+fn exports_to_custom_resources() {
+    let (endpoint, requests) = mock_server(1);
+    let resource = MonitoredResource {
+        r#type: "cloud_run_revision".to_string(),
+        labels: [("project_id", "my-project"), ("service_name", "my-service")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+    let exporter = CloudLoggingExporter::new("my-project", "stdout")
+        .with_endpoint(endpoint)
+        .with_resource(resource);
+
+    let subscriber = Registry::default().with(tracing_stackdriver::layer().with_writer(exporter));
+    tracing::subscriber::with_default(subscriber, || tracing::info!("hello!"));
+
+    let (authorization, batch) = requests.recv().expect("No batch received");
+    assert!(authorization.is_none());
+    assert_eq!(batch["resource"]["type"], "cloud_run_revision");
+    assert_eq!(batch["resource"]["labels"]["service_name"], "my-service");
+    assert_eq!(batch["entries"][0]["jsonPayload"]["message"], "hello!");
+}
+
+#[test]
+fn exports_to_resource_projects() {
+    let (endpoint, requests) = mock_server(1);
+    let exporter =
+        CloudLoggingExporter::from_resource(MonitoredResource::global("other-project"), "stdout")
+            .with_endpoint(endpoint);
+
+    let subscriber = Registry::default().with(tracing_stackdriver::layer().with_writer(exporter));
+    tracing::subscriber::with_default(subscriber, || tracing::info!("hello!"));
+
+    let (_, batch) = requests.recv().expect("No batch received");
+    assert_eq!(batch["logName"], "projects/other-project/logs/stdout");
+    assert_eq!(batch["resource"]["labels"]["project_id"], "other-project");
+}
+
+#[test]
+fn exports_without_blocking_events() {
+    // connections are queued by the listener, but only answered once every event has been emitted
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    let endpoint = format!(
+        "http://{}/v2/entries:write",
+        listener
+            .local_addr()
+            .expect("Couldn't read mock server address")
+    );
+    let exporter = CloudLoggingExporter::new("my-project", "stdout")
+        .with_endpoint(endpoint)
+        .with_batch_size(1);
+
+    let subscriber = Registry::default().with(tracing_stackdriver::layer().with_writer(exporter));
+    let start = Instant::now();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("first");
+        thread::sleep(Duration::from_millis(100));
+        tracing::info!("second");
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let requests = serve(listener, 2);
+        let (_, batch) = requests.recv().expect("No batch received");
+        assert_eq!(batch["entries"][0]["jsonPayload"]["message"], "first");
+        let (_, batch) = requests.recv().expect("No batch received");
+        assert_eq!(batch["entries"][0]["jsonPayload"]["message"], "second");
+    });
+}
+
+#[test]
+fn times_out_unresponsive_endpoints() {
+    // connections are queued by the listener, but never answered
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    let endpoint = format!(
+        "http://{}/v2/entries:write",
+        listener
+            .local_addr()
+            .expect("Couldn't read mock server address")
+    );
+    let mut exporter = CloudLoggingExporter::new("my-project", "stdout")
+        .with_endpoint(endpoint)
+        .with_timeout(Duration::from_millis(200));
+
+    let start = Instant::now();
+    exporter
+        .write_all(b"{\"message\":\"hello!\"}\n")
+        .expect("Couldn't buffer entry");
+
+    assert!(exporter.flush().is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn drops_batches_when_the_queue_is_full() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    let endpoint = format!(
+        "http://{}/v2/entries:write",
+        listener
+            .local_addr()
+            .expect("Couldn't read mock server address")
+    );
+    let exporter = CloudLoggingExporter::new("my-project", "stdout")
+        .with_endpoint(endpoint)
+        .with_batch_size(1)
+        .with_queue_capacity(1)
+        .with_timeout(Duration::from_millis(200));
+
+    let subscriber =
+        Registry::default().with(tracing_stackdriver::layer().with_writer(exporter.clone()));
+
+    tracing::subscriber::with_default(subscriber, || {
+        for index in 0..10 {
+            tracing::info!(index, "hello!");
+        }
+    });
+
+    // at most one batch is being sent and one batch is queued while the endpoint is unresponsive
+    assert!(exporter.dropped_entries() >= 8);
+}
@@ -0,0 +1,134 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use tracing_stackdriver::{
+    entry::{HttpRequest, Span, StackdriverEntry},
+    FieldNaming, LogSeverity,
+};
+
+mod helpers;
+
+fn emit_event() {
+    let span = tracing::info_span!("request", user_id = 123, "span.field" = true);
+    let _guard = span.enter();
+
+    tracing::info!(
+        user_ID = "abc",
+        alreadyCamel = 1,
+        nested.field_name = "nested",
+        http_request.request_method = "GET",
+        labels.team_name = "platform",
+        "some message"
+    );
+}
+
+fn keys<'a, V: 'a>(map: impl IntoIterator<Item = (&'a String, V)>) -> Vec<&'a str> {
+    let mut keys = map
+        .into_iter()
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>();
+
+    keys.sort();
+    keys
+}
+
+fn http_request(event: &StackdriverEntry) -> &HttpRequest {
+    event.http_request.as_ref().expect("No request heard")
+}
+
+fn span(event: &StackdriverEntry) -> &Span {
+    let span = event.span.as_ref().expect("No span heard");
+    assert_eq!(span.name.as_deref(), Some("request"));
+    span
+}
+
+#[test]
+fn uses_camel_case_by_default() {
+    let events = run_with_tracing::<StackdriverEntry>(emit_event)
+        .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.fields["userID"], "abc");
+    assert_eq!(event.fields["alreadyCamel"], 1);
+    assert_eq!(event.fields["nestedFieldName"], "nested");
+    assert_eq!(event.message.as_deref(), Some("some message"));
+    assert_eq!(http_request(event).request_method.as_deref(), Some("GET"));
+    assert!(http_request(event).fields.is_empty());
+    assert_eq!(keys(&event.labels), ["teamName"]);
+    // span fields keep their recorded keys unless a policy is configured
+    assert_eq!(keys(&span(event).fields), ["span.field", "user_id"]);
+}
+
+#[test]
+fn converts_to_camel_case() {
+    let layer = tracing_stackdriver::layer().with_field_naming(FieldNaming::CamelCase);
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, emit_event)
+        .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.fields["userID"], "abc");
+    assert_eq!(event.fields["nestedFieldName"], "nested");
+    assert_eq!(http_request(event).request_method.as_deref(), Some("GET"));
+    assert!(http_request(event).fields.is_empty());
+    assert_eq!(keys(&span(event).fields), ["spanField", "userId"]);
+}
+
+#[test]
+fn converts_to_snake_case() {
+    let layer = tracing_stackdriver::layer().with_field_naming(FieldNaming::SnakeCase);
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, emit_event)
+        .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.fields["user_id"], "abc");
+    assert_eq!(event.fields["already_camel"], 1);
+    assert_eq!(event.fields["nested_field_name"], "nested");
+    assert_eq!(keys(&http_request(event).fields), ["request_method"]);
+    assert_eq!(keys(&event.labels), ["team_name"]);
+    assert_eq!(keys(&span(event).fields), ["span_field", "user_id"]);
+}
+
+#[test]
+fn preserves_field_names() {
+    let layer = tracing_stackdriver::layer().with_field_naming(FieldNaming::Preserve);
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, emit_event)
+        .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.fields["user_ID"], "abc");
+    assert_eq!(event.fields["alreadyCamel"], 1);
+    assert_eq!(event.fields["nested.field_name"], "nested");
+    assert_eq!(keys(&http_request(event).fields), ["request_method"]);
+    assert_eq!(keys(&event.labels), ["team_name"]);
+    assert_eq!(keys(&span(event).fields), ["span.field", "user_id"]);
+}
+
+#[test]
+fn converts_with_custom_functions() {
+    let layer = tracing_stackdriver::layer()
+        .with_field_naming(FieldNaming::custom(|name| name.to_uppercase()));
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, emit_event)
+        .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.fields["USER_ID"], "abc");
+    assert_eq!(event.fields["ALREADYCAMEL"], 1);
+    assert_eq!(event.message.as_deref(), Some("some message"));
+    assert_eq!(event.severity, LogSeverity::Info);
+    assert_eq!(keys(&http_request(event).fields), ["REQUEST_METHOD"]);
+    assert_eq!(keys(&event.labels), ["TEAM_NAME"]);
+    assert_eq!(keys(&span(event).fields), ["SPAN.FIELD", "USER_ID"]);
+}
+
+#[test]
+fn names_truncation_labels() {
+    let layer = tracing_stackdriver::layer()
+        .with_field_naming(FieldNaming::SnakeCase)
+        .with_max_entry_size(256);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!("{}", "a".repeat(1024))
+    })
+    .expect("Error converting test buffer to JSON");
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(keys(&event.labels), ["original_size", "truncated"]);
+}
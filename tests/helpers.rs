@@ -26,6 +26,15 @@ pub fn run_with_tracing_layer<E>(
 where
     E: for<'a> Deserialize<'a>,
 {
+    let output = run_with_tracing_output(layer, callback);
+
+    serde_json::Deserializer::from_str(&output)
+        .into_iter()
+        .collect()
+}
+
+/// Run a traced callback against a Layer configuration, returning the raw output of the Layer
+pub fn run_with_tracing_output(layer: Layer<Registry>, callback: impl FnOnce()) -> String {
     let buffer = Arc::new(Mutex::new(vec![]));
     let shared = buffer.clone();
     let make_writer = move || MockWriter(shared.clone());
@@ -38,9 +47,7 @@ where
         .lock()
         .expect("Couldn't get lock on test write target");
 
-    serde_json::Deserializer::from_slice(&buffer)
-        .into_iter()
-        .collect()
+    String::from_utf8(buffer.clone()).expect("Layer output should be UTF-8")
 }
 
 // FIXME: make this entirely internal
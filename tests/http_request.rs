@@ -1,8 +1,8 @@
-use helpers::run_with_tracing;
-use mocks::{MockHttpEvent, MockHttpRequest};
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::{entry::StackdriverEntry, HttpRequestInheritance};
 
 mod helpers;
-mod mocks;
 
 #[test]
 fn nests_http_request() {
@@ -11,14 +11,15 @@ fn nests_http_request() {
     let remote_ip = "192.168.1.1";
     let status = 200;
 
-    let mock_http_request = MockHttpRequest {
-        request_method: request_method.to_string(),
-        latency: latency.to_string(),
-        remote_ip: remote_ip.to_string(),
-        status,
+    let expected_http_request = tracing_stackdriver::entry::HttpRequest {
+        request_method: Some(request_method.to_string()),
+        latency: Some(latency.to_string()),
+        remote_ip: Some(remote_ip.to_string()),
+        status: Some(status),
+        ..Default::default()
     };
 
-    let events = run_with_tracing::<MockHttpEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             http_request.request_method = &request_method,
             http_request.latency = &latency,
@@ -30,5 +31,133 @@ fn nests_http_request() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert_eq!(event.http_request, mock_http_request);
+    assert_eq!(event.http_request, Some(expected_http_request));
+}
+
+fn emit_request_events() {
+    let span = tracing::info_span!(
+        "request",
+        http_request.request_method = "GET",
+        http_request.request_url = "/users",
+        http_request.remote_ip = tracing::field::Empty,
+    );
+    let _guard = span.enter();
+    span.record("http_request.remote_ip", "192.168.1.1");
+
+    let inner = tracing::info_span!("handler", http_request.request_url = "/users/1");
+    let _inner = inner.enter();
+
+    tracing::info!("handling request");
+    tracing::info!(
+        http_request.status = 200,
+        http_request.latency = "0.23s",
+        "finished request"
+    );
+}
+
+fn http_requests(events: Vec<Map<String, Value>>) -> Vec<Option<Value>> {
+    events
+        .into_iter()
+        .map(|mut event| event.remove("httpRequest"))
+        .collect()
+}
+
+#[test]
+fn inherits_http_request_fields_from_spans() {
+    let events = run_with_tracing::<Map<String, Value>>(emit_request_events)
+        .expect("Error converting test buffer to JSON");
+
+    let span_request = json!({
+        "requestMethod": "GET",
+        "requestUrl": "/users/1",
+        "remoteIp": "192.168.1.1",
+    });
+
+    assert_eq!(
+        http_requests(events),
+        [
+            Some(span_request),
+            Some(json!({
+                "requestMethod": "GET",
+                "requestUrl": "/users/1",
+                "remoteIp": "192.168.1.1",
+                "status": 200,
+                "latency": "0.23s",
+            })),
+        ]
+    );
+}
+
+#[test]
+fn inherits_http_request_fields_with_status_only() {
+    let layer = tracing_stackdriver::layer()
+        .with_http_request_inheritance(HttpRequestInheritance::WithStatus);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, emit_request_events)
+        .expect("Error converting test buffer to JSON");
+
+    let requests = http_requests(events);
+    assert_eq!(requests[0], None);
+    assert_eq!(requests[1].as_ref().unwrap()["requestMethod"], "GET");
+    assert_eq!(requests[1].as_ref().unwrap()["status"], 200);
+}
+
+#[test]
+fn skips_http_request_inheritance() {
+    let layer =
+        tracing_stackdriver::layer().with_http_request_inheritance(HttpRequestInheritance::Never);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, emit_request_events)
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        http_requests(events),
+        [None, Some(json!({ "status": 200, "latency": "0.23s" }))]
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn nests_http_requests_from_http_types() {
+    let request = http::Request::builder()
+        .method(http::Method::PUT)
+        .uri("/users/1?expand=true")
+        .header(http::header::HOST, "example.com")
+        .header(http::header::CONTENT_LENGTH, "42")
+        .header(http::header::USER_AGENT, "test-agent")
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-for", "10.0.0.1")
+        .body(())
+        .unwrap();
+    let response = http::Response::builder()
+        .status(http::StatusCode::CREATED)
+        .header(http::header::CONTENT_LENGTH, "7")
+        .body(())
+        .unwrap();
+
+    let http_request = tracing_stackdriver::HttpRequest::from(&request)
+        .with_response(&response)
+        .with_latency(std::time::Duration::from_millis(1234));
+
+    let events = run_with_tracing::<StackdriverEntry>(
+        || tracing::info!(http_request = %http_request, "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.http_request,
+        Some(tracing_stackdriver::entry::HttpRequest {
+            request_method: Some("PUT".to_string()),
+            request_url: Some("https://example.com/users/1?expand=true".to_string()),
+            request_size: Some(42),
+            response_size: Some(7),
+            status: Some(201),
+            user_agent: Some("test-agent".to_string()),
+            remote_ip: Some("10.0.0.1".to_string()),
+            latency: Some("1.234s".to_string()),
+            protocol: Some("HTTP/1.1".to_string()),
+            ..Default::default()
+        })
+    );
 }
@@ -0,0 +1,9 @@
+#![cfg(feature = "log")]
+#[test]
+fn installs_subscriber_and_log_bridge_once() {
+    tracing_stackdriver::try_init().expect("Couldn't install subscriber");
+
+    assert!(tracing::dispatcher::has_been_set());
+    assert_eq!(log::max_level(), log::LevelFilter::Trace);
+    assert!(tracing_stackdriver::try_init().is_err());
+}
@@ -1,14 +1,13 @@
 use helpers::run_with_tracing;
-use mocks::MockDefaultEvent;
+use tracing_stackdriver::entry::StackdriverEntry;
 
 mod helpers;
-mod mocks;
 
 #[test]
 fn includes_custom_insert_ids() {
     let insert_id = "my-new-event".to_string();
     let events =
-        run_with_tracing::<MockDefaultEvent>(|| tracing::info!(insert_id = insert_id, "hello!"))
+        run_with_tracing::<StackdriverEntry>(|| tracing::info!(insert_id = insert_id, "hello!"))
             .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
@@ -20,7 +19,7 @@ fn includes_custom_insert_ids() {
 fn stringifies_primitive_insert_id_values() {
     let insert_id = 123;
     let events =
-        run_with_tracing::<MockDefaultEvent>(|| tracing::info!(insert_id = insert_id, "hello!"))
+        run_with_tracing::<StackdriverEntry>(|| tracing::info!(insert_id = insert_id, "hello!"))
             .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
@@ -30,7 +29,7 @@ fn stringifies_primitive_insert_id_values() {
 
 #[test]
 fn omits_insert_id_by_default() {
-    let events = run_with_tracing::<MockDefaultEvent>(|| tracing::info!("hello!"))
+    let events = run_with_tracing::<StackdriverEntry>(|| tracing::info!("hello!"))
         .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
@@ -1,9 +1,8 @@
 use helpers::run_with_tracing;
-use mocks::MockDefaultEvent;
 use std::collections::BTreeMap;
+use tracing_stackdriver::entry::StackdriverEntry;
 
 mod helpers;
-mod mocks;
 
 #[test]
 fn nests_labels() {
@@ -11,7 +10,7 @@ fn nests_labels() {
     labels.insert("foo", "bar".to_string());
     labels.insert("baz", "luhrmann".to_string());
 
-    let events = run_with_tracing::<MockDefaultEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             labels.foo = labels.get("foo"),
             labels.baz = labels.get("baz"),
@@ -21,9 +20,9 @@ fn nests_labels() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert!(event.labels.get("foo").is_some());
+    assert!(event.labels.contains_key("foo"));
     assert_eq!(event.labels.get("foo"), labels.get("foo"));
-    assert!(event.labels.get("baz").is_some());
+    assert!(event.labels.contains_key("baz"));
     assert_eq!(event.labels.get("baz"), labels.get("baz"));
 }
 
@@ -32,7 +31,7 @@ fn stringifies_primitive_label_values() {
     let number = 2;
     let boolean = false;
     let string = "a short note";
-    let events = run_with_tracing::<MockDefaultEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             labels.number = number,
             labels.boolean = boolean,
@@ -50,9 +49,58 @@ fn stringifies_primitive_label_values() {
 
 #[test]
 fn omits_labels_by_default() {
-    let events = run_with_tracing::<MockDefaultEvent>(|| tracing::info!("hello!"))
+    let events = run_with_tracing::<StackdriverEntry>(|| tracing::info!("hello!"))
         .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
     assert!(event.labels.is_empty());
 }
+
+#[test]
+fn inherits_labels_from_spans() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let outer = tracing::info_span!(
+            "outer",
+            labels.tenant = "acme",
+            labels.region = "us",
+            labels.job_id = tracing::field::Empty
+        );
+        let _outer = outer.enter();
+        outer.record("labels.job_id", 42);
+
+        let inner = tracing::info_span!("inner", labels.region = "eu");
+        let _inner = inner.enter();
+
+        tracing::info!("from inner span");
+        tracing::info!(labels.tenant = "globex", "with event override");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let expected = |tenant: &str| {
+        BTreeMap::from([
+            ("jobId".to_string(), "42".to_string()),
+            ("region".to_string(), "eu".to_string()),
+            ("tenant".to_string(), tenant.to_string()),
+        ])
+    };
+
+    assert_eq!(events[0].labels, expected("acme"));
+    assert_eq!(events[1].labels, expected("globex"));
+}
+
+#[test]
+fn scopes_span_labels_to_their_spans() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        tracing::info_span!("request", labels.tenant = "acme").in_scope(|| {
+            tracing::info!("inside");
+        });
+        tracing::info!("outside");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        events[0].labels.get("tenant").map(String::as_str),
+        Some("acme")
+    );
+    assert!(events[1].labels.is_empty());
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "log")]
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use std::sync::Once;
+
+mod helpers;
+
+type Event = serde_json::Map<String, serde_json::Value>;
+
+/// The `log` bridge is process-wide, so it's installed once for every test
+fn with_log_bridge<T>(callback: impl FnOnce() -> T) -> T {
+    static BRIDGE: Once = Once::new();
+
+    BRIDGE.call_once(|| {
+        tracing_log::LogTracer::init().expect("Couldn't install log bridge");
+    });
+
+    callback()
+}
+
+#[test]
+fn uses_metadata_of_log_records() {
+    let events = with_log_bridge(|| {
+        run_with_tracing::<Event>(|| log::warn!(target: "dependency", "careful now"))
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["severity"], "WARNING");
+    assert_eq!(event["target"], "dependency");
+    assert_eq!(event["message"], "careful now");
+    assert_eq!(event["modulePath"], "log_bridge");
+    assert_eq!(
+        event["logging.googleapis.com/sourceLocation"]["file"],
+        "tests/log_bridge.rs"
+    );
+    assert!(event["logging.googleapis.com/sourceLocation"]["line"].is_string());
+    assert!(!event.contains_key("logTarget"));
+    assert!(!event.contains_key("logFile"));
+    assert!(!event.contains_key("logLine"));
+}
+
+#[test]
+fn omits_log_source_locations_when_disabled() {
+    let layer = tracing_stackdriver::layer().with_source_location(false);
+
+    let events =
+        with_log_bridge(|| run_with_tracing_layer::<Event>(layer, || log::info!("hello!")))
+            .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("logging.googleapis.com/sourceLocation"));
+}
+
+#[test]
+fn leaves_tracing_events_untouched() {
+    let events = run_with_tracing::<Event>(|| tracing::info!("hello!"))
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["target"], "log_bridge");
+    assert!(!event.contains_key("modulePath"));
+}
@@ -0,0 +1,141 @@
+use helpers::MockWriter;
+use std::{
+    io::{self, Write},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use tracing_stackdriver::{entry::StackdriverEntry, NonBlocking, OverflowPolicy};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+mod helpers;
+
+/// Writer that reports when it starts writing each entry, then waits to be released
+struct GatedWriter {
+    output: Arc<Mutex<Vec<u8>>>,
+    started: mpsc::Sender<()>,
+    release: mpsc::Receiver<()>,
+}
+
+impl Write for GatedWriter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.started.send(()).ok();
+        self.release.recv().ok();
+        self.output.lock().unwrap().write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Queue entries behind a stalled worker, returning everything written after shutdown
+fn write_with_overflow(overflow_policy: OverflowPolicy) -> (String, usize) {
+    let output = Arc::new(Mutex::new(vec![]));
+    let (started, started_receiver) = mpsc::channel();
+    let (release_sender, release) = mpsc::channel();
+    let gated_writer = GatedWriter {
+        output: output.clone(),
+        started,
+        release,
+    };
+
+    let (mut writer, guard) = NonBlocking::builder()
+        .with_queue_capacity(2)
+        .with_batch_size(1)
+        .with_overflow_policy(overflow_policy)
+        .finish(gated_writer);
+
+    writer.write_all(b"1\n").unwrap();
+    started_receiver.recv().unwrap();
+
+    for entry in [b"2\n", b"3\n", b"4\n"] {
+        writer.write_all(entry).unwrap();
+    }
+
+    let dropped = writer.dropped_entries();
+
+    for _ in 0..4 {
+        release_sender.send(()).unwrap();
+    }
+
+    drop(guard);
+
+    let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+    (output, dropped)
+}
+
+#[test]
+fn drains_queue_on_shutdown() {
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let (make_writer, guard) = NonBlocking::builder()
+        .with_flush_interval(Duration::from_secs(60))
+        .finish(MockWriter(buffer.clone()));
+
+    let subscriber =
+        Registry::default().with(tracing_stackdriver::layer().with_writer(make_writer));
+
+    tracing::subscriber::with_default(subscriber, || {
+        for index in 0..100 {
+            tracing::info!(index, "hello!");
+        }
+    });
+
+    drop(guard);
+
+    let events = serde_json::Deserializer::from_slice(&buffer.lock().unwrap())
+        .into_iter::<StackdriverEntry>()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 100);
+}
+
+#[test]
+fn drops_newest_entries_on_overflow() {
+    let (output, dropped) = write_with_overflow(OverflowPolicy::DropNewest);
+    assert_eq!(output, "1\n2\n3\n");
+    assert_eq!(dropped, 1);
+}
+
+#[test]
+fn drops_oldest_entries_on_overflow() {
+    let (output, dropped) = write_with_overflow(OverflowPolicy::DropOldest);
+    assert_eq!(output, "1\n3\n4\n");
+    assert_eq!(dropped, 1);
+}
+
+#[test]
+fn blocks_on_overflow() {
+    let output = Arc::new(Mutex::new(vec![]));
+    let (started, started_receiver) = mpsc::channel();
+    let (release_sender, release) = mpsc::channel();
+    let gated_writer = GatedWriter {
+        output: output.clone(),
+        started,
+        release,
+    };
+
+    let (mut writer, guard) = NonBlocking::builder()
+        .with_queue_capacity(1)
+        .with_batch_size(1)
+        .with_overflow_policy(OverflowPolicy::Block)
+        .finish(gated_writer);
+
+    writer.write_all(b"1\n").unwrap();
+    started_receiver.recv().unwrap();
+    writer.write_all(b"2\n").unwrap();
+
+    let blocked = std::thread::spawn(move || {
+        writer.write_all(b"3\n").unwrap();
+        writer.dropped_entries()
+    });
+
+    for _ in 0..3 {
+        release_sender.send(()).unwrap();
+    }
+
+    assert_eq!(blocked.join().unwrap(), 0);
+    drop(guard);
+
+    assert_eq!(output.lock().unwrap().as_slice(), b"1\n2\n3\n");
+}
@@ -13,7 +13,6 @@ use tracing_stackdriver::CloudTraceConfiguration;
 use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt};
 
 mod helpers;
-mod mocks;
 
 static PROJECT_ID: &str = "my_project_123";
 
@@ -0,0 +1,92 @@
+use helpers::run_with_tracing;
+use tracing_stackdriver::entry::{Operation, StackdriverEntry};
+
+mod helpers;
+
+#[test]
+fn nests_operation_fields() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        tracing::info!(
+            operation.id = "job-1",
+            operation.producer = "worker",
+            operation.first = true,
+            "hello!"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.operation,
+        Some(Operation {
+            id: Some("job-1".to_string()),
+            producer: Some("worker".to_string()),
+            first: Some(true),
+            last: None,
+        })
+    );
+}
+
+#[test]
+fn omits_operation_by_default() {
+    let events = run_with_tracing::<StackdriverEntry>(|| tracing::info!("hello!"))
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.operation.is_none());
+}
+
+#[test]
+fn groups_entries_within_operation_spans() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let span =
+            tracing::info_span!("job", operation.id = "job-1", operation.producer = "worker");
+        let _guard = span.enter();
+        tracing::info!("started");
+        tracing::info_span!("step").in_scope(|| tracing::info!("working"));
+        tracing::info!("finished");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let operations = events
+        .iter()
+        .map(|event| {
+            let operation = event.operation.as_ref().expect("Missing operation");
+            assert_eq!(operation.id.as_deref(), Some("job-1"));
+            assert_eq!(operation.producer.as_deref(), Some("worker"));
+            (event.message.as_deref(), operation.first, operation.last)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        operations,
+        [
+            (Some("started"), Some(true), None),
+            (Some("working"), None, None),
+            (Some("finished"), None, None),
+            (Some("job"), None, Some(true)),
+        ]
+    );
+}
+
+#[test]
+fn closes_operations_recorded_after_span_creation() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let span = tracing::info_span!("job", operation.id = tracing::field::Empty);
+        span.record("operation.id", "job-2");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(events.len(), 1);
+    assert_eq!(event.message.as_deref(), Some("job"));
+    assert_eq!(
+        event.operation,
+        Some(Operation {
+            id: Some("job-2".to_string()),
+            producer: None,
+            first: Some(true),
+            last: Some(true),
+        })
+    );
+}
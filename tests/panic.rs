@@ -0,0 +1,95 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Once,
+    },
+};
+use tracing_stackdriver::{entry::StackdriverEntry, LogSeverity, ServiceContext};
+
+mod helpers;
+
+/// Number of panics seen by the hook that was installed before the Stackdriver hook
+static PREVIOUS_HOOK_PANICS: AtomicUsize = AtomicUsize::new(0);
+
+fn install_panic_hooks() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        panic::set_hook(Box::new(|_| {
+            PREVIOUS_HOOK_PANICS.fetch_add(1, Ordering::SeqCst);
+        }));
+        tracing_stackdriver::install_panic_hook();
+    });
+}
+
+fn panic_within_span() -> u32 {
+    install_panic_hooks();
+
+    let span = tracing::info_span!("handler");
+    let _guard = span.enter();
+    let line = line!() + 1;
+    let result = panic::catch_unwind(|| panic!("boom: {}", 42));
+    assert!(result.is_err());
+
+    line
+}
+
+#[test]
+fn logs_panics_as_critical_events() {
+    let mut line = 0;
+    let events = run_with_tracing::<StackdriverEntry>(|| line = panic_within_span())
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.severity, LogSeverity::Critical);
+    assert_eq!(event.target, "panic");
+    assert_eq!(event.message.as_deref(), Some("boom: 42"));
+
+    let stack_trace = event.stack_trace.as_deref().expect("No stack trace heard");
+    assert!(stack_trace.starts_with("boom: 42\n"));
+    assert!(stack_trace.len() > "boom: 42\n".len());
+
+    let source_location = event.source_location.as_ref().expect("No location heard");
+    assert!(source_location.file.ends_with("panic.rs"));
+    assert_eq!(source_location.line, Some(line.to_string()));
+    assert_eq!(
+        event.span.as_ref().and_then(|span| span.name.as_deref()),
+        Some("handler")
+    );
+    assert!(event.entry_type.is_none());
+}
+
+#[test]
+fn reports_panics_to_error_reporting() {
+    let layer =
+        tracing_stackdriver::layer().with_error_reporting(ServiceContext::new("my-service"));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        panic_within_span();
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.entry_type.as_deref(),
+        Some("type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent")
+    );
+    assert!(event
+        .stack_trace
+        .as_deref()
+        .is_some_and(|stack_trace| stack_trace.starts_with("boom: 42\n")));
+}
+
+#[test]
+fn runs_the_previous_panic_hook() {
+    let before = PREVIOUS_HOOK_PANICS.load(Ordering::SeqCst);
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        panic_within_span();
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 1);
+    assert!(PREVIOUS_HOOK_PANICS.load(Ordering::SeqCst) > before);
+}
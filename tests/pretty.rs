@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+use tracing_stackdriver::{CloudTraceConfiguration, Format, Layer};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+/// Run a traced callback against a Layer configuration, returning the lines it wrote
+fn run_with_pretty_layer(layer: Layer<Registry>, callback: impl FnOnce()) -> Vec<String> {
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let layer = layer.with_writer(move || SharedBuffer(shared.clone()));
+
+    tracing::subscriber::with_default(Registry::default().with(layer), callback);
+
+    let buffer = buffer
+        .lock()
+        .expect("Couldn't get lock on test write target");
+    String::from_utf8(buffer.clone())
+        .expect("Output is not valid UTF-8")
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn renders_one_line_per_entry() {
+    let lines = run_with_pretty_layer(
+        tracing_stackdriver::layer().with_format(Format::Pretty),
+        || {
+            tracing::info!(user_id = 42, "hello!");
+            tracing::warn!("goodbye!");
+        },
+    );
+
+    assert_eq!(lines.len(), 2);
+
+    let (time, rest) = lines[0].split_once(' ').unwrap();
+    assert!(time.ends_with('Z'), "unexpected time {time}");
+    assert!(
+        rest.starts_with("     INFO pretty hello! userId=42 at tests/pretty.rs:"),
+        "unexpected line {rest}"
+    );
+    assert!(lines[1].contains("  WARNING pretty goodbye! at tests/pretty.rs:"));
+    assert!(!lines.iter().any(|line| line.contains('\x1b')));
+}
+
+#[test]
+fn renders_special_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_format(Format::Pretty)
+        .with_source_location(false)
+        .with_cloud_trace(CloudTraceConfiguration {
+            project_id: PROJECT_ID.to_owned(),
+        });
+
+    let lines = run_with_pretty_layer(layer, || {
+        tracing::info_span!(
+            "request",
+            trace_context = format!("{TRACE_ID}/1;o=1"),
+            http_request.request_method = "GET"
+        )
+        .in_scope(|| {
+            tracing::info_span!("db_query").in_scope(|| {
+                tracing::error!(
+                    labels.service = "checkout",
+                    http_request.status = 500,
+                    severity = "critical",
+                    "query failed"
+                )
+            })
+        });
+    });
+
+    let line = lines.first().expect("No entry heard");
+    let (_, line) = line.split_once(' ').unwrap();
+
+    assert_eq!(
+        line,
+        format!(
+            " CRITICAL pretty [request > db_query] query failed \
+             httpRequest{{requestMethod=\"GET\" status=500}} \
+             labels{{service=\"checkout\"}} \
+             spanId=\"0000000000000001\" \
+             trace=\"projects/{PROJECT_ID}/traces/{TRACE_ID}\" \
+             trace_sampled=true"
+        )
+    );
+}
+
+#[test]
+fn colorizes_with_ansi() {
+    let lines = run_with_pretty_layer(tracing_stackdriver::layer().pretty(), || {
+        tracing::warn!("careful!")
+    });
+
+    let line = lines.first().expect("No entry heard");
+    assert!(
+        line.contains("\x1b[33m  WARNING\x1b[0m"),
+        "unexpected line {line:?}"
+    );
+    assert!(line.contains("\x1b[1mpretty\x1b[0m"));
+}
+
+#[test]
+fn renders_truncated_entries() {
+    let layer = tracing_stackdriver::layer()
+        .with_format(Format::Pretty)
+        .with_source_location(false)
+        .with_max_entry_size(512);
+
+    let lines = run_with_pretty_layer(layer, || tracing::info!("{}", "a".repeat(1024)));
+
+    let line = lines.first().expect("No entry heard");
+    assert!(
+        line.len() < 512,
+        "line of length {} not truncated",
+        line.len()
+    );
+    assert!(
+        line.contains("truncated=\"true\""),
+        "unexpected line {line}"
+    );
+}
@@ -0,0 +1,188 @@
+#![cfg(feature = "redaction")]
+use helpers::run_with_tracing_layer;
+use regex::Regex;
+use tracing_stackdriver::{entry::StackdriverEntry, Redaction, Replacement};
+
+mod helpers;
+
+#[test]
+fn redacts_fields_matching_patterns() {
+    let layer = tracing_stackdriver::layer().with_redaction(
+        Redaction::new()
+            .with_field_pattern("*password*")
+            .with_field_pattern("token"),
+    );
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(
+            user_password = "hunter2",
+            auth.token = "abc123",
+            attempts = 3,
+            "logging in"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.fields["userPassword"], "[REDACTED]");
+    assert_eq!(event.fields["authToken"], "[REDACTED]");
+    assert_eq!(event.fields["attempts"], 3);
+    assert_eq!(event.message.as_deref(), Some("logging in"));
+}
+
+#[test]
+fn scrubs_values_matching_regular_expressions() {
+    let email = Regex::new(r"[\w.+-]+@[\w-]+\.[\w.]+").unwrap();
+    let card = Regex::new(r"\b\d{4}(?:[ -]?\d{4}){3}\b").unwrap();
+    let layer = tracing_stackdriver::layer()
+        .with_redaction(Redaction::new().with_scrubber(email).with_scrubber(card));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(
+            labels.contact = "ferris@example.com",
+            "charged 4242 4242 4242 4242 for ferris@example.com"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.message.as_deref(),
+        Some("charged [REDACTED] for [REDACTED]")
+    );
+    assert_eq!(event.labels["contact"], "[REDACTED]");
+}
+
+#[test]
+fn replaces_redacted_values_with_hashes() {
+    let layer = tracing_stackdriver::layer().with_redaction(
+        Redaction::new()
+            .with_field_pattern("email")
+            .with_replacement(Replacement::hash("my-secret-key")),
+    );
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(email = "ferris@example.com", "first");
+        tracing::info!(email = "ferris@example.com", "second");
+        tracing::info!(email = "corro@example.com", "third");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let hashes = events
+        .iter()
+        .map(|event| event.fields["email"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(hashes[0], "[HASH:b863ab77d6558587]");
+    assert_eq!(hashes[0], hashes[1]);
+    assert_ne!(hashes[0], hashes[2]);
+}
+
+#[test]
+fn keys_hashes_with_a_secret() {
+    let layer = tracing_stackdriver::layer().with_redaction(
+        Redaction::new()
+            .with_field_pattern("email")
+            .with_replacement(Replacement::hash("another-key")),
+    );
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(email = "ferris@example.com")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.fields["email"], "[HASH:f21ccd4e5b129098]");
+    assert_eq!(
+        format!("{:?}", Replacement::hash("another-key")),
+        "Hash(HashKey(..))"
+    );
+}
+
+#[test]
+fn redacts_span_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_redaction(Redaction::new().with_field_pattern("*secret*"));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        let span = tracing::info_span!(
+            "request",
+            client_secret = "shh",
+            labels.api_secret = "shh",
+            client = "cli",
+            session_secret = tracing::field::Empty
+        );
+        span.record("session_secret", "shh");
+        let _guard = span.enter();
+        tracing::info!("handling request");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let span = event.span.as_ref().expect("No span heard");
+    assert_eq!(span.fields["client_secret"], "[REDACTED]");
+    assert_eq!(span.fields["session_secret"], "[REDACTED]");
+    assert_eq!(span.fields["client"], "cli");
+    assert_eq!(event.spans[0].fields["client_secret"], "[REDACTED]");
+    assert_eq!(event.labels["apiSecret"], "[REDACTED]");
+}
+
+#[test]
+fn redacts_url_query_parameters() {
+    let layer =
+        tracing_stackdriver::layer().with_redaction(Redaction::new().with_field_pattern("*token*"));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(
+            http_request.request_method = "GET",
+            http_request.request_url = "https://example.com/login?access_token=abc&page=2#top",
+            "logged in"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let http_request = event.http_request.as_ref().expect("No request heard");
+    assert_eq!(
+        http_request.request_url.as_deref(),
+        Some("https://example.com/login?access_token=[REDACTED]&page=2#top")
+    );
+    assert_eq!(http_request.request_method.as_deref(), Some("GET"));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn redacts_whole_http_requests() {
+    let mut http_request = tracing_stackdriver::HttpRequest::new();
+    http_request.request_url = Some("https://example.com/?session_token=abc".parse().unwrap());
+    http_request.user_agent = Some("test-agent".to_string());
+
+    let layer =
+        tracing_stackdriver::layer().with_redaction(Redaction::new().with_field_pattern("*token*"));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(
+        layer,
+        || tracing::info!(http_request = %http_request, "logged in"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let http_request = event.http_request.as_ref().expect("No request heard");
+    assert_eq!(
+        http_request.request_url.as_deref(),
+        Some("https://example.com/?session_token=[REDACTED]")
+    );
+    assert_eq!(http_request.user_agent.as_deref(), Some("test-agent"));
+}
+
+#[test]
+fn leaves_fields_untouched_by_default() {
+    let layer = tracing_stackdriver::layer().with_redaction(Redaction::new());
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(password = "hunter2", "logging in")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.fields["password"], "hunter2");
+}
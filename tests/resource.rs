@@ -0,0 +1,288 @@
+use helpers::run_with_tracing_layer;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::Mutex,
+    thread,
+};
+use tracing_stackdriver::{MonitoredResource, ResourceDetector};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+static DETECTED_VARIABLES: [&str; 10] = [
+    "GOOGLE_CLOUD_PROJECT",
+    "K_SERVICE",
+    "K_REVISION",
+    "K_CONFIGURATION",
+    "FUNCTION_TARGET",
+    "KUBERNETES_SERVICE_HOST",
+    "NAMESPACE",
+    "POD_NAME",
+    "CONTAINER_NAME",
+    "GCE_METADATA_HOST",
+];
+
+/// Environment variables are process-wide, so tests that depend on them take turns
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+fn with_environment<T>(variables: &[(&str, &str)], callback: impl FnOnce() -> T) -> T {
+    let _lock = ENVIRONMENT
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+
+    for key in DETECTED_VARIABLES {
+        std::env::remove_var(key);
+    }
+
+    for (key, value) in variables {
+        std::env::set_var(key, value);
+    }
+
+    callback()
+}
+
+/// Start a stand-in metadata server that answers requests for the provided paths,
+/// returning its address
+fn mock_metadata_server(values: &[(&str, &str)]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    let address = listener
+        .local_addr()
+        .expect("Couldn't read mock server address")
+        .to_string();
+    let values = values
+        .iter()
+        .map(|(path, value)| (format!("/computeMetadata/v1/{path}"), value.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("Couldn't accept connection");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut flavor = None;
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if let Some(("Metadata-Flavor", value)) = line.split_once(": ") {
+                    flavor = Some(value.to_string());
+                }
+            }
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+            let response = match (values.get(path), flavor.as_deref()) {
+                (Some(value), Some("Google")) => format!(
+                    "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{value}",
+                    value.len()
+                ),
+                _ => "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            };
+
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    address
+}
+
+/// An address with nothing listening on it
+fn unavailable_metadata_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind mock server");
+    listener.local_addr().unwrap().to_string()
+}
+
+fn labels(resource: &MonitoredResource) -> Vec<(&str, &str)> {
+    resource
+        .labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+#[test]
+fn detects_cloud_run_revisions() {
+    let host = mock_metadata_server(&[
+        ("project/project-id", PROJECT_ID),
+        ("instance/region", "projects/1234/regions/us-central1"),
+    ]);
+
+    let resource = with_environment(
+        &[
+            ("K_SERVICE", "my-service"),
+            ("K_REVISION", "my-service-00001-abc"),
+            ("K_CONFIGURATION", "my-service"),
+        ],
+        || ResourceDetector::new().with_metadata_host(host).detect(),
+    )
+    .expect("Couldn't detect resource");
+
+    assert_eq!(resource.r#type, "cloud_run_revision");
+    assert_eq!(
+        labels(&resource),
+        [
+            ("configuration_name", "my-service"),
+            ("location", "us-central1"),
+            ("project_id", PROJECT_ID),
+            ("revision_name", "my-service-00001-abc"),
+            ("service_name", "my-service"),
+        ]
+    );
+}
+
+#[test]
+fn detects_cloud_functions() {
+    let host = mock_metadata_server(&[("instance/region", "projects/1234/regions/europe-west1")]);
+
+    let resource = with_environment(
+        &[
+            ("GOOGLE_CLOUD_PROJECT", PROJECT_ID),
+            ("FUNCTION_TARGET", "handler"),
+            ("K_SERVICE", "my-function"),
+            ("K_REVISION", "my-function-00001-abc"),
+        ],
+        || ResourceDetector::new().with_metadata_host(host).detect(),
+    )
+    .expect("Couldn't detect resource");
+
+    assert_eq!(resource.r#type, "cloud_function");
+    assert_eq!(
+        labels(&resource),
+        [
+            ("function_name", "my-function"),
+            ("project_id", PROJECT_ID),
+            ("region", "europe-west1"),
+        ]
+    );
+}
+
+#[test]
+fn detects_gke_containers() {
+    let host = mock_metadata_server(&[
+        ("project/project-id", PROJECT_ID),
+        ("instance/attributes/cluster-name", "my-cluster"),
+        ("instance/attributes/cluster-location", "us-east1-b"),
+    ]);
+
+    let resource = with_environment(
+        &[
+            ("KUBERNETES_SERVICE_HOST", "10.0.0.1"),
+            ("NAMESPACE", "default"),
+            ("POD_NAME", "my-pod"),
+            ("CONTAINER_NAME", "app"),
+        ],
+        || ResourceDetector::new().with_metadata_host(host).detect(),
+    )
+    .expect("Couldn't detect resource");
+
+    assert_eq!(resource.r#type, "k8s_container");
+    assert_eq!(
+        labels(&resource),
+        [
+            ("cluster_name", "my-cluster"),
+            ("container_name", "app"),
+            ("location", "us-east1-b"),
+            ("namespace_name", "default"),
+            ("pod_name", "my-pod"),
+            ("project_id", PROJECT_ID),
+        ]
+    );
+}
+
+#[test]
+fn detects_gce_instances_from_environment_host() {
+    let host = mock_metadata_server(&[
+        ("project/project-id", PROJECT_ID),
+        ("instance/id", "1234567890"),
+        ("instance/zone", "projects/1234/zones/us-central1-a"),
+    ]);
+
+    let resource = with_environment(&[("GCE_METADATA_HOST", &host)], || {
+        ResourceDetector::new().detect()
+    })
+    .expect("Couldn't detect resource");
+
+    assert_eq!(resource.r#type, "gce_instance");
+    assert_eq!(
+        labels(&resource),
+        [
+            ("instance_id", "1234567890"),
+            ("project_id", PROJECT_ID),
+            ("zone", "us-central1-a"),
+        ]
+    );
+}
+
+#[test]
+fn falls_back_to_global_resources() {
+    let host = unavailable_metadata_server();
+
+    let resource = with_environment(&[("GOOGLE_CLOUD_PROJECT", PROJECT_ID)], || {
+        ResourceDetector::new().with_metadata_host(host).detect()
+    })
+    .expect("Couldn't detect resource");
+
+    assert_eq!(resource, MonitoredResource::global(PROJECT_ID));
+}
+
+#[test]
+fn fails_without_a_project_id() {
+    let host = unavailable_metadata_server();
+
+    let result = with_environment(&[], || {
+        ResourceDetector::new().with_metadata_host(host).detect()
+    });
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn bounds_metadata_requests_by_the_timeout() {
+    let started = std::time::Instant::now();
+
+    let result = with_environment(&[], || {
+        ResourceDetector::new()
+            .with_metadata_host("metadata.google.internal")
+            .with_timeout(std::time::Duration::from_millis(100))
+            .detect()
+    });
+
+    assert!(result.is_err());
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+}
+
+#[derive(Debug, Deserialize)]
+struct MockEventWithTrace {
+    #[serde(rename = "logging.googleapis.com/trace")]
+    trace_id: String,
+}
+
+#[test]
+fn uses_resource_project_id_for_cloud_trace() {
+    let layer = tracing_stackdriver::layer()
+        .with_monitored_resource(&MonitoredResource::global(PROJECT_ID));
+
+    let events = run_with_tracing_layer::<MockEventWithTrace>(layer, || {
+        let span = tracing::info_span!("request", trace_context = TRACE_ID);
+        let _guard = span.enter();
+        tracing::info!("handling request");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.trace_id,
+        format!("projects/{PROJECT_ID}/traces/{TRACE_ID}")
+    );
+}
@@ -0,0 +1,239 @@
+use helpers::run_with_tracing_layer;
+use std::time::Duration;
+use tracing::Level;
+use tracing_stackdriver::{
+    entry::StackdriverEntry, CloudTraceConfiguration, LogSeverity, Sampling, SeverityMapper,
+};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+fn messages(events: &[StackdriverEntry]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|event| event.message.as_deref().unwrap())
+        .collect()
+}
+
+#[test]
+fn rate_limits_each_callsite() {
+    let layer = tracing_stackdriver::layer().with_sampling(Sampling::new().with_rate_limit(0.0, 2));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for attempt in 0..5 {
+            tracing::warn!("hot loop {attempt}");
+        }
+
+        for attempt in 0..2 {
+            tracing::info!("cold loop {attempt}");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        messages(&events),
+        [
+            "hot loop 0",
+            "hot loop 1",
+            "cold loop 0",
+            "cold loop 1",
+            "3 entries suppressed by sampling"
+        ]
+    );
+}
+
+#[test]
+fn samples_severities_by_probability() {
+    let layer = tracing_stackdriver::layer().with_sampling(
+        Sampling::new()
+            .with_probability(LogSeverity::Debug, 0.0)
+            .with_probability(LogSeverity::Info, 0.5),
+    );
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for _ in 0..1000 {
+            tracing::debug!("debugging");
+            tracing::info!("informing");
+        }
+
+        tracing::warn!("warning");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let messages = messages(&events);
+    let kept = messages
+        .iter()
+        .filter(|message| **message == "informing")
+        .count();
+
+    assert!(!messages.contains(&"debugging"));
+    assert!((350..650).contains(&kept), "kept {kept} of 1000 entries");
+    assert!(messages.contains(&"warning"));
+    assert!(messages.contains(&"1000 entries suppressed by sampling"));
+}
+
+#[test]
+fn samples_severities_as_they_are_written() {
+    let layer = tracing_stackdriver::layer()
+        .with_sampling(Sampling::new().with_probability(LogSeverity::Notice, 0.0))
+        .with_severity_mapper(SeverityMapper::new().with_target(
+            "audit",
+            Level::INFO,
+            LogSeverity::Notice,
+        ));
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(target: "audit", "audited");
+        tracing::info!("informing");
+        tracing::warn!(severity = "notice", "noticed");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        messages(&events),
+        [
+            "informing",
+            "1 entries suppressed by sampling",
+            "1 entries suppressed by sampling"
+        ]
+    );
+}
+
+#[test]
+fn keeps_entries_in_sampled_traces() {
+    let layer = tracing_stackdriver::layer()
+        .with_sampling(
+            Sampling::new()
+                .with_probability(LogSeverity::Info, 0.0)
+                .with_rate_limit(0.0, 0),
+        )
+        .with_cloud_trace(CloudTraceConfiguration {
+            project_id: PROJECT_ID.to_owned(),
+        });
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info_span!("sampled", trace_context = format!("{TRACE_ID}/1;o=1"))
+            .in_scope(|| tracing::info!("kept"));
+        tracing::info_span!("unsampled", trace_context = format!("{TRACE_ID}/1;o=0"))
+            .in_scope(|| tracing::info!("dropped"));
+        tracing::info!("dropped");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        messages(&events),
+        [
+            "kept",
+            "1 entries suppressed by sampling",
+            "1 entries suppressed by sampling"
+        ]
+    );
+    assert!(events[0].trace.trace_sampled);
+}
+
+#[test]
+fn summarizes_suppressed_entries() {
+    let layer = tracing_stackdriver::layer().with_sampling(
+        Sampling::new()
+            .with_rate_limit(0.0, 1)
+            .with_summary_interval(Duration::from_millis(50)),
+    );
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for _ in 0..4 {
+            tracing::warn!(target: "hot", "hot loop");
+        }
+
+        std::thread::sleep(Duration::from_millis(80));
+        tracing::info!("later");
+
+        // summaries that are still pending are written when the Layer is dropped
+        for _ in 0..2 {
+            tracing::warn!("cold loop");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        messages(&events),
+        [
+            "hot loop",
+            "3 entries suppressed by sampling",
+            "later",
+            "cold loop",
+            "1 entries suppressed by sampling"
+        ]
+    );
+
+    let summary = &events[1];
+    assert_eq!(summary.severity, LogSeverity::Warning);
+    assert_eq!(summary.fields["samplingTarget"], "hot");
+    assert_eq!(summary.fields["samplingSuppressed"], 3);
+    assert_eq!(
+        summary
+            .source_location
+            .as_ref()
+            .map(|location| location.file.as_str()),
+        Some("tests/sampling.rs")
+    );
+}
+
+#[test]
+fn writes_summaries_outside_of_the_current_span() {
+    let layer = tracing_stackdriver::layer()
+        .with_sampling(
+            Sampling::new()
+                .with_rate_limit(0.0, 1)
+                .with_summary_interval(Duration::from_millis(50)),
+        )
+        .with_cloud_trace(CloudTraceConfiguration {
+            project_id: PROJECT_ID.to_owned(),
+        });
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        for _ in 0..2 {
+            tracing::warn!("hot loop");
+        }
+
+        std::thread::sleep(Duration::from_millis(80));
+
+        tracing::info_span!(
+            "request",
+            labels.tenant = "a",
+            operation.id = "request-1",
+            trace_context = format!("{TRACE_ID}/1;o=0")
+        )
+        .in_scope(|| tracing::info!("later"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        messages(&events),
+        [
+            "hot loop",
+            "1 entries suppressed by sampling",
+            "later",
+            "request"
+        ]
+    );
+
+    let summary = &events[1];
+    assert!(summary.span.is_none(), "summary inherited span");
+    assert!(summary.spans.is_empty(), "summary inherited spans");
+    assert!(summary.labels.is_empty(), "summary inherited labels");
+    assert!(summary.operation.is_none(), "summary inherited operation");
+    assert!(summary.trace.trace.is_none(), "summary inherited trace");
+
+    let later = &events[2];
+    assert_eq!(later.labels["tenant"], "a");
+    assert_eq!(
+        later
+            .operation
+            .as_ref()
+            .and_then(|operation| operation.first),
+        Some(true)
+    );
+    assert!(later.trace.trace.is_some());
+}
@@ -0,0 +1,91 @@
+use helpers::run_with_tracing_layer;
+use serde::Deserialize;
+use tracing::Level;
+use tracing_stackdriver::{LogSeverity, SeverityMapper};
+
+mod helpers;
+
+#[derive(Debug, Deserialize)]
+struct MockEventWithSeverity {
+    severity: String,
+}
+
+fn severities(severity_mapper: SeverityMapper, callback: impl FnOnce()) -> Vec<String> {
+    let layer = tracing_stackdriver::layer().with_severity_mapper(severity_mapper);
+
+    run_with_tracing_layer::<MockEventWithSeverity>(layer, callback)
+        .expect("Error converting test buffer to JSON")
+        .into_iter()
+        .map(|event| event.severity)
+        .collect()
+}
+
+#[test]
+fn maps_levels_for_every_target() {
+    let severities = severities(
+        SeverityMapper::new().with_level(Level::TRACE, LogSeverity::Default),
+        || {
+            tracing::trace!("tracing");
+            tracing::debug!("debugging");
+        },
+    );
+
+    assert_eq!(severities, ["DEFAULT", "DEBUG"]);
+}
+
+#[test]
+fn maps_levels_for_targets_and_their_modules() {
+    let severities = severities(
+        SeverityMapper::new()
+            .with_level(Level::INFO, LogSeverity::Debug)
+            .with_target("audit", Level::INFO, LogSeverity::Notice)
+            .with_target("audit::admin", Level::INFO, LogSeverity::Alert),
+        || {
+            tracing::info!(target: "audit", "audited");
+            tracing::info!(target: "audit::users", "audited user");
+            tracing::info!(target: "audit::admin", "audited admin");
+            tracing::info!(target: "auditing", "not audited");
+            tracing::warn!(target: "audit", "warned");
+        },
+    );
+
+    assert_eq!(
+        severities,
+        ["NOTICE", "NOTICE", "ALERT", "DEBUG", "WARNING"]
+    );
+}
+
+#[test]
+fn maps_severities_with_custom_functions() {
+    let severities = severities(
+        SeverityMapper::new().with_custom(|_, fields, severity| match fields.get("alert") {
+            Some(serde_json::Value::Bool(true)) => LogSeverity::Alert,
+            _ => severity,
+        }),
+        || {
+            tracing::info!(alert = true, "wake up");
+            tracing::info!(alert = false, "sleep in");
+            tracing::warn!(severity = "critical", "overridden");
+        },
+    );
+
+    assert_eq!(severities, ["ALERT", "INFO", "CRITICAL"]);
+}
+
+#[test]
+fn maps_severity_field_overrides() {
+    let severities = severities(
+        SeverityMapper::new()
+            .with_target("audit", Level::INFO, LogSeverity::Notice)
+            .with_custom(|meta, _, severity| match meta.target() {
+                "legacy" => severity.min(LogSeverity::Warning),
+                _ => severity,
+            }),
+        || {
+            tracing::info!(target: "audit", severity = "error", "explicit");
+            tracing::info!(target: "legacy", severity = "emergency", "dramatic");
+        },
+    );
+
+    assert_eq!(severities, ["ERROR", "WARNING"]);
+}
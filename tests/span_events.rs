@@ -0,0 +1,81 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use tracing_stackdriver::{entry::StackdriverEntry, CloudTraceConfiguration, LogSeverity};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+fn seconds(duration: &serde_json::Value) -> f64 {
+    duration
+        .as_str()
+        .and_then(|duration| duration.strip_suffix('s'))
+        .expect("Durations should be formatted in seconds")
+        .parse()
+        .expect("Durations should be numeric")
+}
+
+#[test]
+fn omits_span_close_entries_by_default() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        tracing::info_span!("job").in_scope(|| tracing::info!("working"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn writes_entries_when_spans_close() {
+    let layer = tracing_stackdriver::layer().with_span_close_entries(true);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        let span = tracing::debug_span!("job", job_id = 42);
+        span.in_scope(|| std::thread::sleep(std::time::Duration::from_millis(10)));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 1);
+
+    let event = &events[0];
+    assert_eq!(event.severity, LogSeverity::Debug);
+    assert_eq!(event.message.as_deref(), Some("job"));
+
+    let span = event.span.as_ref().expect("No span heard");
+    assert_eq!(span.name.as_deref(), Some("job"));
+    assert_eq!(span.fields["job_id"], 42);
+    assert!(seconds(&event.fields["timeBusy"]) >= 0.01);
+    assert!(seconds(&event.fields["timeIdle"]) >= 0.01);
+    assert!(event.source_location.is_none());
+}
+
+#[test]
+fn includes_trace_fields_in_span_close_entries() {
+    let layer = tracing_stackdriver::layer()
+        .with_span_close_entries(true)
+        .with_cloud_trace(CloudTraceConfiguration {
+            project_id: PROJECT_ID.to_owned(),
+        });
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        let request = tracing::info_span!("request", trace_context = format!("{TRACE_ID}/1"));
+        let _guard = request.enter();
+        tracing::info_span!("query").in_scope(|| {});
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let names = events
+        .iter()
+        .map(|event| event.message.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["query", "request"]);
+
+    for event in events {
+        assert_eq!(
+            event.trace.trace,
+            Some(format!("projects/{PROJECT_ID}/traces/{TRACE_ID}"))
+        );
+        assert_eq!(event.trace.span_id.as_deref(), Some("0000000000000001"));
+    }
+}
@@ -0,0 +1,124 @@
+use helpers::{run_with_tracing_layer, run_with_tracing_output};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing_stackdriver::{entry::StackdriverEntry, FieldNaming, LogSeverity};
+
+mod helpers;
+
+fn labels<const N: usize>(labels: [(&str, &str); N]) -> BTreeMap<String, String> {
+    labels
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+#[test]
+fn includes_defaults_in_every_entry() {
+    let layer = tracing_stackdriver::layer()
+        .with_default_labels([("service", "checkout"), ("region", "us-east1")])
+        .with_static_fields([("version", json!("1.2.3")), ("git_sha", json!("abc123"))]);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!("outside of any span");
+        tracing::info_span!("request").in_scope(|| tracing::warn!("inside a span"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+
+    for event in events {
+        assert_eq!(
+            event.labels,
+            labels([("service", "checkout"), ("region", "us-east1")])
+        );
+        assert_eq!(event.fields["version"], "1.2.3");
+        assert_eq!(event.fields["gitSha"], "abc123");
+    }
+}
+
+#[test]
+fn prefers_event_and_span_values() {
+    let layer = tracing_stackdriver::layer()
+        .with_default_labels([("service", "checkout"), ("region", "us-east1")])
+        .with_static_fields([("version", json!("1.2.3")), ("git_sha", json!("abc123"))]);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info_span!("request", labels.region = "europe-west1")
+            .in_scope(|| tracing::info!(labels.service = "payments", version = "2.0.0", "hello!"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(
+        event.labels,
+        labels([("service", "payments"), ("region", "europe-west1")])
+    );
+    assert_eq!(event.fields["version"], "2.0.0");
+    assert_eq!(event.fields["gitSha"], "abc123");
+}
+
+#[test]
+fn compares_static_fields_with_renamed_event_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_field_naming(FieldNaming::CamelCase)
+        .with_static_fields([("gitSha", "abc123")]);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(git_sha = "def456", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].fields["gitSha"], "def456");
+}
+
+#[test]
+fn prefers_event_values_under_the_naming_policy() {
+    let layer = tracing_stackdriver::layer()
+        .with_default_labels([("job_id", "default")])
+        .with_static_fields([("git_sha", "abc123")]);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        tracing::info!(labels.job_id = "event", git_sha = "def456", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event.labels, labels([("jobId", "event")]));
+    assert_eq!(event.fields["gitSha"], "def456");
+    assert!(!event.fields.contains_key("git_sha"));
+}
+
+#[test]
+fn renames_defaults_with_the_naming_policy() {
+    let layer = tracing_stackdriver::layer()
+        .with_field_naming(FieldNaming::SnakeCase)
+        .with_default_labels([("gitSha", "abc123")])
+        .with_static_fields([("buildId", "42")]);
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || tracing::info!("hello!"))
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].labels, labels([("git_sha", "abc123")]));
+    assert_eq!(events[0].fields["build_id"], "42");
+}
+
+#[test]
+fn skips_static_fields_with_reserved_keys() {
+    let layer = tracing_stackdriver::layer().with_static_fields([
+        ("severity", json!("DEBUG")),
+        ("message", json!("static")),
+        ("logging.googleapis.com/trace", json!("some-trace")),
+        ("version", json!("1.2.3")),
+    ]);
+
+    let output = run_with_tracing_output(layer, || tracing::warn!("hello!"));
+    let event: StackdriverEntry = serde_json::from_str(&output).expect("Invalid entry");
+
+    assert_eq!(output.matches("\"severity\"").count(), 1);
+    assert_eq!(event.severity, LogSeverity::Warning);
+    assert_eq!(event.message.as_deref(), Some("hello!"));
+    assert!(event.trace.trace.is_none());
+    assert_eq!(event.fields["version"], "1.2.3");
+}
@@ -0,0 +1,185 @@
+#![cfg(feature = "tower")]
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+};
+use tower::{service_fn, Service, ServiceBuilder, ServiceExt};
+use tracing_stackdriver::{
+    entry::StackdriverEntry, CloudTraceConfiguration, LogSeverity, RequestLoggingLayer,
+};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+/// Waker for futures that never wait on anything
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Poll a future that never waits on anything to completion
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut context = Context::from_waker(&waker);
+
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("Mock services should complete immediately"),
+    }
+}
+
+fn handle(request: http::Request<String>) -> http::Response<String> {
+    let mut service = ServiceBuilder::new()
+        .layer(RequestLoggingLayer::new())
+        .service(service_fn(|request: http::Request<String>| async move {
+            tracing::info!("handling request");
+
+            let status = match request.uri().path() {
+                "/missing" => http::StatusCode::NOT_FOUND,
+                "/broken" => http::StatusCode::INTERNAL_SERVER_ERROR,
+                _ => http::StatusCode::OK,
+            };
+
+            http::Response::builder()
+                .status(status)
+                .body("hello!".to_string())
+        }));
+
+    let service = block_on(service.ready()).expect("Service should be ready");
+    block_on(service.call(request)).expect("Service should respond")
+}
+
+fn request(path: &str) -> http::request::Builder {
+    http::Request::builder()
+        .method(http::Method::POST)
+        .uri(path)
+        .version(http::Version::HTTP_2)
+        .header(http::header::HOST, "example.com")
+        .header(http::header::CONTENT_LENGTH, "4")
+        .header(http::header::USER_AGENT, "test-agent")
+        .header(http::header::REFERER, "https://example.com/referer")
+        .header("x-forwarded-for", "10.0.0.1, 10.0.0.2")
+}
+
+#[test]
+fn logs_http_requests() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        handle(request("/users?page=2").body("data".to_string()).unwrap());
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].message.as_deref(), Some("handling request"));
+    assert_eq!(
+        events[0]
+            .span
+            .as_ref()
+            .and_then(|span| span.name.as_deref()),
+        Some("request")
+    );
+
+    let event = &events[1];
+    assert_eq!(event.severity, LogSeverity::Info);
+    assert_eq!(
+        event.message.as_deref(),
+        Some("POST http://example.com/users?page=2")
+    );
+
+    let http_request = event.http_request.as_ref().expect("No request heard");
+    assert_eq!(http_request.request_method.as_deref(), Some("POST"));
+    assert_eq!(
+        http_request.request_url.as_deref(),
+        Some("http://example.com/users?page=2")
+    );
+    assert_eq!(http_request.request_size, Some(4));
+    assert_eq!(http_request.response_size, Some(6));
+    assert_eq!(http_request.status, Some(200));
+    assert_eq!(http_request.user_agent.as_deref(), Some("test-agent"));
+    assert_eq!(http_request.remote_ip.as_deref(), Some("10.0.0.1"));
+    assert_eq!(
+        http_request.referer.as_deref(),
+        Some("https://example.com/referer")
+    );
+    assert_eq!(http_request.protocol.as_deref(), Some("HTTP/2"));
+
+    let latency = http_request
+        .latency
+        .as_deref()
+        .and_then(|latency| latency.strip_suffix('s'))
+        .expect("Latency should be formatted in seconds");
+    assert!(latency.parse::<f64>().is_ok());
+}
+
+#[test]
+fn derives_severity_from_status() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        handle(request("/missing").body(String::new()).unwrap());
+        handle(request("/broken").body(String::new()).unwrap());
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let severities = events
+        .iter()
+        .filter(|event| event.http_request.is_some())
+        .map(|event| event.severity)
+        .collect::<Vec<_>>();
+    assert_eq!(severities, [LogSeverity::Warning, LogSeverity::Error]);
+}
+
+#[test]
+fn attaches_trace_context_from_headers() {
+    let layer = tracing_stackdriver::layer().with_cloud_trace(CloudTraceConfiguration {
+        project_id: PROJECT_ID.to_owned(),
+    });
+
+    let events = run_with_tracing_layer::<StackdriverEntry>(layer, || {
+        handle(
+            request("/")
+                .header("x-cloud-trace-context", format!("{TRACE_ID}/1;o=1"))
+                .body(String::new())
+                .unwrap(),
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let trace = format!("projects/{PROJECT_ID}/traces/{TRACE_ID}");
+    assert_eq!(events.len(), 2);
+
+    for event in events {
+        assert_eq!(event.trace.trace.as_deref(), Some(trace.as_str()));
+        assert_eq!(event.trace.span_id.as_deref(), Some("0000000000000001"));
+    }
+}
+
+#[test]
+fn includes_work_done_in_call_in_latency() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let mut service = ServiceBuilder::new()
+            .layer(RequestLoggingLayer::new())
+            .service(service_fn(|_: http::Request<String>| {
+                // work done before the response future is returned
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                async { http::Response::builder().body(String::new()) }
+            }));
+
+        let service = block_on(service.ready()).expect("Service should be ready");
+        block_on(service.call(request("/").body(String::new()).unwrap()))
+            .expect("Service should respond");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let latency = events[0]
+        .http_request
+        .as_ref()
+        .and_then(|http_request| http_request.latency.as_deref())
+        .and_then(|latency| latency.strip_suffix('s'))
+        .expect("Latency should be formatted in seconds");
+    assert!(latency.parse::<f64>().unwrap() >= 0.05);
+}
@@ -0,0 +1,137 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use tracing_stackdriver::{
+    entry::{StackdriverEntry, TraceFields},
+    CloudTraceConfiguration, CloudTraceContext,
+};
+
+mod helpers;
+
+static PROJECT_ID: &str = "my_project_123";
+static TRACE_ID: &str = "105445aa7843bc8bf206b12000100000";
+
+fn run_with_cloud_trace(callback: impl FnOnce()) -> Vec<TraceFields> {
+    let layer = tracing_stackdriver::layer().with_cloud_trace(CloudTraceConfiguration {
+        project_id: PROJECT_ID.to_owned(),
+    });
+
+    run_with_tracing_layer::<StackdriverEntry>(layer, callback)
+        .expect("Error converting test buffer to JSON")
+        .into_iter()
+        .map(|event| event.trace)
+        .collect()
+}
+
+#[test]
+fn parses_cloud_trace_headers() {
+    let trace_context: CloudTraceContext = format!("{TRACE_ID}/1;o=1").parse().unwrap();
+    assert_eq!(trace_context.trace_id, TRACE_ID);
+    assert_eq!(trace_context.span_id.as_deref(), Some("0000000000000001"));
+    assert!(trace_context.sampled);
+    assert_eq!(trace_context.to_string(), format!("{TRACE_ID}/1;o=1"));
+
+    let trace_context = CloudTraceContext::from_cloud_trace_header(TRACE_ID).unwrap();
+    assert_eq!(trace_context.span_id, None);
+    assert!(!trace_context.sampled);
+
+    assert!(CloudTraceContext::from_cloud_trace_header("not-a-trace/1;o=1").is_err());
+    assert!(CloudTraceContext::from_cloud_trace_header(&format!("{TRACE_ID}/abc")).is_err());
+}
+
+#[test]
+fn parses_traceparent_headers() {
+    let header = format!("00-{TRACE_ID}-00f067aa0ba902b7-01");
+    let trace_context: CloudTraceContext = header.parse().unwrap();
+    assert_eq!(trace_context.trace_id, TRACE_ID);
+    assert_eq!(trace_context.span_id.as_deref(), Some("00f067aa0ba902b7"));
+    assert!(trace_context.sampled);
+
+    let unsampled =
+        CloudTraceContext::from_traceparent(&format!("00-{TRACE_ID}-00f067aa0ba902b7-00"));
+    assert!(!unsampled.unwrap().sampled);
+
+    for invalid in [
+        format!("ff-{TRACE_ID}-00f067aa0ba902b7-01"),
+        format!("00-{TRACE_ID}-0000000000000000-01"),
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01".to_string(),
+        format!("00-{TRACE_ID}-00f067aa0ba902b7"),
+        format!("00-{TRACE_ID}-00f067aa0ba902b7-01-extra"),
+    ] {
+        assert!(
+            CloudTraceContext::from_traceparent(&invalid).is_err(),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn includes_trace_fields_from_cloud_trace_header() {
+    let events = run_with_cloud_trace(|| {
+        let span = tracing::info_span!("request", trace_context = %format!("{TRACE_ID}/12345;o=1"));
+        let _guard = span.enter();
+        tracing::info!("handling request");
+    });
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.trace.as_deref(),
+        Some(format!("projects/{PROJECT_ID}/traces/{TRACE_ID}").as_str())
+    );
+    assert_eq!(event.span_id.as_deref(), Some("0000000000003039"));
+    assert!(event.trace_sampled);
+}
+
+#[test]
+fn includes_trace_fields_in_nested_spans() {
+    let header = format!("00-{TRACE_ID}-00f067aa0ba902b7-00");
+
+    let events = run_with_cloud_trace(|| {
+        tracing::info!("before request");
+        let span = tracing::info_span!("request", trace_context = header.as_str());
+        let _guard = span.enter();
+        let inner = tracing::info_span!("inner");
+        let _inner = inner.enter();
+        tracing::info!("handling request");
+    });
+
+    let before = events.first().expect("No event heard");
+    assert!(before.trace.is_none());
+
+    let event = events.last().expect("No event heard");
+    assert_eq!(
+        event.trace.as_deref(),
+        Some(format!("projects/{PROJECT_ID}/traces/{TRACE_ID}").as_str())
+    );
+    assert_eq!(event.span_id.as_deref(), Some("00f067aa0ba902b7"));
+    assert!(!event.trace_sampled);
+}
+
+#[test]
+fn includes_trace_fields_recorded_later() {
+    let events = run_with_cloud_trace(|| {
+        let span = tracing::info_span!("request", trace_context = tracing::field::Empty);
+        let _guard = span.enter();
+        span.record("trace_context", format!("{TRACE_ID};o=1").as_str());
+        tracing::info!("handling request");
+    });
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.trace.as_deref(),
+        Some(format!("projects/{PROJECT_ID}/traces/{TRACE_ID}").as_str())
+    );
+    assert!(event.span_id.is_none());
+    assert!(event.trace_sampled);
+}
+
+#[test]
+fn omits_trace_fields_without_configuration() {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
+        let span = tracing::info_span!("request", trace_context = TRACE_ID);
+        let _guard = span.enter();
+        tracing::info!("handling request");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.trace, TraceFields::default());
+}
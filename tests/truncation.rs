@@ -0,0 +1,106 @@
+use helpers::run_with_tracing_output;
+use tracing_stackdriver::entry::StackdriverEntry;
+
+mod helpers;
+
+const MAX_ENTRY_SIZE: usize = 1024;
+
+fn parse(output: &str) -> StackdriverEntry {
+    serde_json::from_str(output).expect("Error converting test buffer to JSON")
+}
+
+#[test]
+fn leaves_small_entries_untouched() {
+    let layer = tracing_stackdriver::layer().with_max_entry_size(MAX_ENTRY_SIZE);
+
+    let event = parse(&run_with_tracing_output(layer, || tracing::info!("hello!")));
+
+    assert_eq!(event.message.as_deref(), Some("hello!"));
+    assert!(event.labels.is_empty());
+}
+
+#[test]
+fn truncates_long_messages_first() {
+    let message = "a".repeat(4 * MAX_ENTRY_SIZE);
+    let layer = tracing_stackdriver::layer().with_max_entry_size(MAX_ENTRY_SIZE);
+
+    let output = run_with_tracing_output(layer, || {
+        let span = tracing::info_span!("outer", foo = "bar");
+        let _guard = span.enter();
+        tracing::info!(labels.baz = "luhrmann", "{}", message);
+    });
+    let event = parse(&output);
+
+    let truncated = event.message.as_deref().unwrap();
+    assert!(truncated.len() < message.len());
+    assert!(truncated.starts_with("aaa"));
+    assert!(truncated.ends_with('…'));
+    assert_eq!(event.span.as_ref().unwrap().fields["foo"], "bar");
+    assert_eq!(event.spans.len(), 1);
+    assert!(output.trim_end().len() <= MAX_ENTRY_SIZE);
+
+    assert_eq!(event.labels["baz"], "luhrmann");
+    assert_eq!(event.labels["truncated"], "true");
+    let original_size: usize = event.labels["originalSize"].parse().unwrap();
+    assert!(original_size > 4 * MAX_ENTRY_SIZE);
+}
+
+#[test]
+fn truncates_large_span_fields_after_messages() {
+    let large = "b".repeat(MAX_ENTRY_SIZE);
+    let layer = tracing_stackdriver::layer().with_max_entry_size(MAX_ENTRY_SIZE);
+
+    let output = run_with_tracing_output(layer, || {
+        let span = tracing::info_span!("outer", large = %large, small = "small");
+        let _guard = span.enter();
+        tracing::info!("hello!");
+    });
+    let event = parse(&output);
+
+    let span = event.span.as_ref().expect("No span heard");
+    assert!(event.message.as_deref().unwrap().starts_with("hello"));
+    assert_eq!(span.fields["small"], "small");
+    assert_eq!(span.name.as_deref(), Some("outer"));
+    assert!(span.fields["large"].as_str().unwrap().len() < large.len());
+    assert!(output.trim_end().len() <= MAX_ENTRY_SIZE);
+    assert_eq!(event.labels["truncated"], "true");
+}
+
+#[test]
+fn drops_spans_as_a_last_resort() {
+    let layer = tracing_stackdriver::layer().with_max_entry_size(MAX_ENTRY_SIZE);
+
+    let output = run_with_tracing_output(layer, || {
+        let _spans = (0..64)
+            .map(|_| tracing::info_span!("a_span_with_a_rather_long_name").entered())
+            .collect::<Vec<_>>();
+        tracing::info!("hello!");
+    });
+    let event = parse(&output);
+
+    assert!(!output.contains("\"spans\":"));
+    assert_eq!(
+        event.span.as_ref().and_then(|span| span.name.as_deref()),
+        Some("a_span_with_a_rather_long_name")
+    );
+    assert!(output.trim_end().len() <= MAX_ENTRY_SIZE);
+    assert_eq!(event.labels["truncated"], "true");
+}
+
+#[test]
+fn truncates_large_fields_as_a_last_resort() {
+    let blob = "x".repeat(5 * MAX_ENTRY_SIZE);
+    let layer = tracing_stackdriver::layer().with_max_entry_size(MAX_ENTRY_SIZE);
+
+    let output = run_with_tracing_output(
+        layer,
+        || tracing::info!(blob = %blob, small = "small", "hello!"),
+    );
+    let event = parse(&output);
+
+    assert!(output.trim_end().len() <= MAX_ENTRY_SIZE);
+    assert!(event.fields["blob"].as_str().unwrap().len() < blob.len());
+    assert_eq!(event.fields["small"], "small");
+    assert_eq!(event.message.as_deref(), Some("hello!"));
+    assert_eq!(event.labels["truncated"], "true");
+}
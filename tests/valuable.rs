@@ -1,18 +1,16 @@
 #![allow(clippy::disallowed_names)]
 #![cfg(all(tracing_unstable, feature = "valuable"))]
-use helpers::run_with_tracing;
-use mocks::{MockDefaultEvent, MockHttpEvent};
+use helpers::{run_with_tracing, run_with_tracing_layer};
 use serde::Deserialize;
 use std::fmt::Debug;
-use tracing_stackdriver::LogSeverity;
+use tracing_stackdriver::{entry::StackdriverEntry, LogSeverity};
 use valuable::Valuable;
 
 mod helpers;
-mod mocks;
 
 #[test]
 fn handles_valuable_severity_override() {
-    let events = run_with_tracing::<MockDefaultEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             severity = LogSeverity::Notice.as_value(),
             "notice me, senpai!"
@@ -21,7 +19,7 @@ fn handles_valuable_severity_override() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
-    assert_eq!(event.severity, "NOTICE");
+    assert_eq!(event.severity, LogSeverity::Notice);
 }
 
 #[test]
@@ -39,7 +37,7 @@ fn validates_structured_http_requests() {
         ..Default::default()
     };
 
-    let events = run_with_tracing::<MockHttpEvent>(|| {
+    let events = run_with_tracing::<StackdriverEntry>(|| {
         tracing::info!(
             http_request = http_request.as_value(),
             "http_request testing"
@@ -48,16 +46,17 @@ fn validates_structured_http_requests() {
     .expect("Error converting test buffer to JSON");
 
     let event = events.first().expect("No event heard");
+    let event_request = event.http_request.as_ref().expect("No httpRequest heard");
     assert_eq!(
-        event.http_request.request_method,
-        request_method.to_string()
+        event_request.request_method,
+        Some(request_method.to_string())
     );
     assert_eq!(
-        event.http_request.latency,
-        format!("{}s", latency.as_secs_f32())
+        event_request.latency,
+        Some(format!("{}s", latency.as_secs_f32()))
     );
-    assert_eq!(event.http_request.status, status.as_u16());
-    assert_eq!(event.http_request.remote_ip, remote_ip.to_string());
+    assert_eq!(event_request.status, Some(status.as_u16()));
+    assert_eq!(event_request.remote_ip, Some(remote_ip.to_string()));
 }
 
 #[derive(Debug, Deserialize, Valuable, PartialEq)]
@@ -90,3 +89,29 @@ fn includes_valuable_structures() {
     let event = events.first().expect("No event heard");
     assert_eq!(event.structured_log, structured_log);
 }
+
+#[derive(Valuable)]
+struct User {
+    name: String,
+    email: String,
+}
+
+#[test]
+fn redacts_nested_valuable_fields() {
+    let user = User {
+        name: "Ferris".to_string(),
+        email: "ferris@example.com".to_string(),
+    };
+    let layer = tracing_stackdriver::layer()
+        .with_redaction(tracing_stackdriver::Redaction::new().with_field_pattern("user.email"));
+
+    let events =
+        run_with_tracing_layer::<serde_json::Map<String, serde_json::Value>>(layer, || {
+            tracing::info!(user = user.as_value(), "signed up")
+        })
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["user"]["name"], "Ferris");
+    assert_eq!(event["user"]["email"], "[REDACTED]");
+}